@@ -0,0 +1,266 @@
+use crate::{
+    merge::Collected,
+    source::{self, AsyncSources},
+    ConfigBuilder, Diff, Error, Loaded, Priority, Reload, Reloadable,
+};
+use serde::de::DeserializeOwned;
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    runtime::Handle,
+    sync::watch,
+    task::{self, JoinSet},
+    time,
+};
+
+/// Runs blocking `f` on tokio's blocking pool, propagating its panics.
+async fn unblock<T, F>(f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+impl ConfigBuilder {
+    /// Like [`build`](Self::build), but reads files without blocking the async runtime.
+    pub async fn build_async<Cfg>(&self) -> Result<Cfg, Error>
+    where
+        Cfg: DeserializeOwned + Send + 'static,
+    {
+        self.build_with_metadata_async()
+            .await
+            .map(Loaded::into_inner)
+    }
+
+    /// Like [`build_with_metadata`](Self::build_with_metadata), but reads files without
+    /// blocking the async runtime and loads the [async sources](Self::add_async_source).
+    pub async fn build_with_metadata_async<Cfg>(&self) -> Result<Loaded<Cfg>, Error>
+    where
+        Cfg: DeserializeOwned + Send + 'static,
+    {
+        let mut config = self.prepare().map_err(Error::Preparation)?;
+        let layers = collect(
+            std::mem::take(&mut config.async_sources),
+            config.async_source_timeout,
+            config.skip_failed_optional_sources,
+        )
+        .await?;
+        unblock(move || config.load(layers)).await
+    }
+
+    /// Like [`reloadable`](Self::reloadable), but reads files without blocking the async
+    /// runtime and loads the [async sources](Self::add_async_source). Sync reloads, such as
+    /// those triggered by file changes or signals, load the async sources on the current
+    /// runtime.
+    pub async fn reloadable_async<Cfg>(&self) -> Result<Reloadable<Cfg>, Error>
+    where
+        Cfg: DeserializeOwned + Send + Sync + 'static,
+    {
+        let loaded = self.build_with_metadata_async().await?;
+        if self
+            .async_sources
+            .as_ref()
+            .is_some_and(|sources| !sources.0.is_empty())
+        {
+            Ok(Reloadable::with_runtime(
+                self.clone(),
+                loaded,
+                Handle::current(),
+            ))
+        } else {
+            Ok(Reloadable::new(self.clone(), loaded))
+        }
+    }
+}
+
+/// Awaits every source concurrently, each within its timeout. The sources still pending are
+/// cancelled once one fails.
+async fn collect(
+    sources: AsyncSources,
+    default_timeout: Duration,
+    skip_failed_optional_sources: bool,
+) -> Result<Vec<(Priority, Collected)>, Error> {
+    let mut tasks = JoinSet::new();
+    for (index, (priority, source)) in sources.0.into_iter().enumerate() {
+        let timeout = source.timeout().unwrap_or(default_timeout);
+        tasks.spawn(async move {
+            let collected = time::timeout(timeout, source.collect()).await;
+            (index, priority, source, timeout, collected)
+        });
+    }
+
+    let mut layers = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        let (index, priority, source, timeout, collected) =
+            joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        let name = source.name();
+        let error = match collected {
+            Ok(Ok(entries)) => {
+                layers.push((index, priority, source::layer(name, entries)));
+                continue;
+            }
+            Ok(Err(e)) => Error::Source(name.to_owned(), e),
+            Err(_) => Error::SourceTimeout(name.to_owned(), timeout),
+        };
+        if !(skip_failed_optional_sources && source.optional()) {
+            // Dropping `tasks` aborts the sources still pending.
+            return Err(error);
+        }
+    }
+    // Sources of the same priority are merged in the order they were added.
+    layers.sort_by_key(|(index, ..)| *index);
+    Ok(layers
+        .into_iter()
+        .map(|(_, priority, layer)| (priority, layer))
+        .collect())
+}
+
+impl<Cfg> Reloadable<Cfg>
+where
+    Cfg: DeserializeOwned + Send + Sync + 'static,
+{
+    /// Like [`reload`](Self::reload), but reads files without blocking the async runtime and
+    /// loads the [async sources](ConfigBuilder::add_async_source).
+    pub async fn reload_async(&self) -> Reload<Cfg> {
+        let reloadable = self.clone();
+        let runtime = Handle::current();
+        // Reloads take turns on a blocking lock, so wait for it off the runtime.
+        unblock(move || {
+            reloadable.reload_with(|builder| runtime.block_on(builder.build_with_metadata_async()))
+        })
+        .await
+    }
+
+    /// Publishes every successfully reloaded config along with what changed, failed reloads
+    /// keep the current value. The initial value comes with an empty diff.
+    pub fn changes(&self) -> watch::Receiver<(Arc<Cfg>, Arc<Diff>)> {
+        let (sender, receiver) = watch::channel((self.load(), Arc::default()));
+        self.on_reload_while(move |reload| {
+            if let Reload::Applied { config, diff } = reload {
+                sender.send_replace((config.clone(), diff.clone()));
+            }
+            !sender.is_closed()
+        });
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AsyncSource, BoxFuture, ConfigBuilder, Error, Priority, Reload, Value};
+    use serde::Deserialize;
+    use std::time::Duration;
+    use tokio::task;
+
+    #[derive(Deserialize)]
+    struct FooConfig {
+        bar: BarConfig,
+    }
+
+    #[derive(Deserialize)]
+    struct BarConfig {
+        baz: u16,
+    }
+
+    #[tokio::test]
+    async fn reload_async() {
+        std::env::set_var("ASYNC_ENV", "production");
+        std::env::set_var("ASYNC_BAR__BAZ", "1");
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("ASYNC_ENV")
+            .environment_variables_source_prefix("ASYNC")
+            .config_directory(format!(
+                "{}/tests/fixtures/layered",
+                env!("CARGO_MANIFEST_DIR")
+            ));
+        assert_eq!(builder.build_async::<FooConfig>().await.unwrap().bar.baz, 1);
+
+        let config = builder.reloadable_async::<FooConfig>().await.unwrap();
+        let mut changes = config.changes();
+        std::env::set_var("ASYNC_BAR__BAZ", "2");
+        config.reload_async().await;
+        changes.changed().await.unwrap();
+        let (reloaded, diff) = changes.borrow_and_update().clone();
+        assert_eq!(reloaded.bar.baz, 2);
+        assert!(diff.touches("bar.baz"));
+
+        std::env::set_var("ASYNC_BAR__BAZ", "x");
+        config.reload_async().await;
+        assert!(!changes.has_changed().unwrap());
+        assert_eq!(config.load().bar.baz, 2);
+    }
+
+    struct Remote {
+        name: &'static str,
+        delay: Duration,
+        optional: bool,
+    }
+
+    impl AsyncSource for Remote {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn collect(
+            &self,
+        ) -> BoxFuture<'_, Result<Vec<(String, Value)>, Box<dyn std::error::Error + Send + Sync>>>
+        {
+            Box::pin(async move {
+                tokio::time::sleep(self.delay).await;
+                Ok(vec![(String::from("bar.baz"), Value::from(5))])
+            })
+        }
+
+        fn optional(&self) -> bool {
+            self.optional
+        }
+    }
+
+    #[tokio::test]
+    async fn async_sources() {
+        std::env::set_var("REMOTE_ENV", "production");
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("REMOTE_ENV")
+            .environment_variables_source_prefix("REMOTE")
+            .config_directory(format!(
+                "{}/tests/fixtures/layered",
+                env!("CARGO_MANIFEST_DIR")
+            ))
+            .async_source_timeout(Duration::from_millis(200))
+            .add_async_source(
+                Priority::AfterFiles,
+                Remote {
+                    name: "http",
+                    delay: Duration::from_millis(10),
+                    optional: false,
+                },
+            );
+        assert_eq!(builder.build_async::<FooConfig>().await.unwrap().bar.baz, 5);
+
+        let err = builder.build::<FooConfig>().err().unwrap();
+        assert!(matches!(err, Error::AsyncSourceInSyncBuild(name) if name == "http"));
+
+        // Sync reloads, e.g. from file watchers or signal handlers, still load async sources.
+        let config = builder.reloadable_async::<FooConfig>().await.unwrap();
+        let reload = task::spawn_blocking(move || config.reload()).await.unwrap();
+        assert!(matches!(reload, Reload::Applied { config, .. } if config.bar.baz == 5));
+
+        builder.add_async_source(
+            Priority::AfterEnvironmentVariables,
+            Remote {
+                name: "slow",
+                delay: Duration::from_secs(60),
+                optional: true,
+            },
+        );
+        let err = builder.build_async::<FooConfig>().await.err().unwrap();
+        assert!(matches!(err, Error::SourceTimeout(name, _) if name == "slow"));
+
+        builder.skip_failed_optional_sources(true);
+        assert_eq!(builder.build_async::<FooConfig>().await.unwrap().bar.baz, 5);
+    }
+}
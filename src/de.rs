@@ -0,0 +1,871 @@
+//! A [`serde::Deserializer`] over merged [`config::Value`]s that keeps track of key paths, so
+//! that errors can point at the offending key and the source that set it.
+//!
+//! When errors are collected, invalid leaves are recorded and replaced by placeholder values so
+//! deserialization can carry on. Errors raised by visitors, such as missing fields, cannot be
+//! recovered in place; the whole struct is deserialized again with a placeholder at that key
+//! until no new errors turn up. Types that validate their input, such as `IpAddr` or `NonZeroU16`,
+//! may reject a placeholder too, so a few placeholder values are tried in turn; a key that rejects
+//! all of them is left out and visited last, so its siblings are still checked.
+
+use crate::secret;
+use config::{ConfigError, Map, Value, ValueKind};
+use serde::de::{self, IntoDeserializer, Visitor};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt, vec,
+};
+
+/// Deserialization error carrying the key path it occurred at.
+#[derive(Debug)]
+pub(crate) struct DeError {
+    pub(crate) key: Option<String>,
+    pub(crate) message: String,
+    pub(crate) expected: Option<String>,
+    /// Field reported by [`de::Error::missing_field`], appended to the key once it is known.
+    missing: Option<&'static str>,
+    /// Whether `message` may quote the value of a [`Secret`](crate::Secret).
+    pub(crate) secret: bool,
+}
+
+thread_local! {
+    /// How many [`Secret`](crate::Secret)s are being deserialized on this thread.
+    static SECRET_DEPTH: Cell<usize> = const { Cell::new(0) };
+    /// Key paths of the [`Secret`](crate::Secret)s deserialized on this thread.
+    static SECRET_KEYS: RefCell<BTreeSet<String>> = const { RefCell::new(BTreeSet::new()) };
+}
+
+/// Marks errors raised while it is alive as being about a secret value.
+struct SecretScope;
+
+impl SecretScope {
+    fn enter() -> Self {
+        SECRET_DEPTH.with(|depth| depth.set(depth.get() + 1));
+        Self
+    }
+}
+
+impl Drop for SecretScope {
+    fn drop(&mut self) {
+        SECRET_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+impl DeError {
+    fn new(message: String, expected: Option<String>) -> Self {
+        Self {
+            key: None,
+            message,
+            expected,
+            missing: None,
+            secret: SECRET_DEPTH.with(|depth| depth.get() > 0),
+        }
+    }
+
+    /// Attaches `key` unless a more specific key is already known.
+    fn at(mut self, key: &str) -> Self {
+        if self.key.is_none() {
+            self.key = Some(match self.missing.take() {
+                Some(field) => join(key, field),
+                None => key.to_owned(),
+            });
+        }
+        self
+    }
+
+    fn from_config(error: ConfigError, expected: &'static str) -> Self {
+        match error {
+            ConfigError::Type { unexpected, .. } => Self::new(
+                format!("invalid type: {unexpected}, expected {expected}"),
+                Some(expected.to_owned()),
+            ),
+            error => Self::new(error.to_string(), Some(expected.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Self::new(message.to_string(), None)
+    }
+
+    fn invalid_type(unexpected: de::Unexpected, expected: &dyn de::Expected) -> Self {
+        Self::new(
+            format!("invalid type: {unexpected}, expected {expected}"),
+            Some(expected.to_string()),
+        )
+    }
+
+    fn invalid_value(unexpected: de::Unexpected, expected: &dyn de::Expected) -> Self {
+        Self::new(
+            format!("invalid value: {unexpected}, expected {expected}"),
+            Some(expected.to_string()),
+        )
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self {
+            missing: Some(field),
+            secret: false,
+            ..Self::new(format!("missing field `{field}`"), None)
+        }
+    }
+}
+
+fn join(key: &str, field: &str) -> String {
+    if key.is_empty() {
+        field.to_owned()
+    } else {
+        format!("{key}.{field}")
+    }
+}
+
+/// Errors collected so far, the keys to replace by placeholders along with the placeholder
+/// attempt to use, and the keys that rejected every placeholder.
+#[derive(Default)]
+struct Recorder {
+    errors: RefCell<Vec<DeError>>,
+    placeholders: RefCell<BTreeMap<String, usize>>,
+    deferred: RefCell<BTreeSet<String>>,
+}
+
+impl Recorder {
+    /// Records `error` unless its key already has one.
+    fn record(&self, error: DeError) {
+        let mut errors = self.errors.borrow_mut();
+        if errors.iter().all(|recorded| recorded.key != error.key) {
+            errors.push(error);
+        }
+    }
+
+    fn placeholder(&self, key: &str) -> Option<Placeholder> {
+        self.placeholders
+            .borrow()
+            .get(key)
+            .copied()
+            .map(Placeholder)
+    }
+
+    fn is_deferred(&self, key: &str) -> bool {
+        self.deferred.borrow().contains(key)
+    }
+
+    /// Whether `key` is a parent of a deferred key.
+    fn leads_to_deferred(&self, key: &str) -> bool {
+        self.deferred.borrow().iter().any(|deferred| {
+            deferred
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.starts_with(['.', '[']))
+        })
+    }
+
+    fn into_errors(self) -> Vec<DeError> {
+        let mut errors = self.errors.into_inner();
+        errors.sort_by(|a, b| a.key.cmp(&b.key));
+        errors
+    }
+}
+
+/// Deserializes `tree`, stopping at the first error unless `collect_errors` is set. Also returns
+/// the key paths of the [`Secret`](crate::Secret)s in `T`.
+pub(crate) fn deserialize<T: de::DeserializeOwned>(
+    tree: Map<String, Value>,
+    collect_errors: bool,
+) -> Result<(T, BTreeSet<String>), Vec<DeError>> {
+    SECRET_KEYS.with(RefCell::take);
+    let value = deserialize_tree(tree, collect_errors)?;
+    Ok((value, SECRET_KEYS.with(RefCell::take)))
+}
+
+fn deserialize_tree<T: de::DeserializeOwned>(
+    tree: Map<String, Value>,
+    collect_errors: bool,
+) -> Result<T, Vec<DeError>> {
+    if !collect_errors {
+        return T::deserialize(Deserializer::new(tree, None)).map_err(|e| vec![e]);
+    }
+
+    let recorder = Recorder::default();
+    loop {
+        match T::deserialize(Deserializer::new(tree.clone(), Some(&recorder))) {
+            Ok(value) if recorder.errors.borrow().is_empty() => return Ok(value),
+            Ok(_) => return Err(recorder.into_errors()),
+            Err(error) => {
+                let key = error.key.clone().unwrap_or_default();
+                if key.is_empty() || recorder.is_deferred(&key) {
+                    recorder.record(error);
+                    return Err(recorder.into_errors());
+                }
+                // An error at a placeholder is a consequence of an error recorded earlier: the
+                // type rejected the placeholder, so try the next one or leave the key out.
+                let attempt = recorder.placeholders.borrow().get(&key).copied();
+                match attempt {
+                    None => {
+                        recorder.record(error);
+                        recorder.placeholders.borrow_mut().insert(key, 0);
+                    }
+                    Some(attempt) if attempt + 1 < Placeholder::ATTEMPTS => {
+                        recorder.placeholders.borrow_mut().insert(key, attempt + 1);
+                    }
+                    Some(_) => {
+                        recorder.deferred.borrow_mut().insert(key);
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct Deserializer<'r> {
+    value: Value,
+    key: String,
+    recorder: Option<&'r Recorder>,
+}
+
+impl<'r> Deserializer<'r> {
+    fn new(tree: Map<String, Value>, recorder: Option<&'r Recorder>) -> Self {
+        Self {
+            value: Value::from(tree),
+            key: String::new(),
+            recorder,
+        }
+    }
+
+    fn convert<T>(
+        &self,
+        expected: &'static str,
+        convert: impl FnOnce(Value) -> Result<T, ConfigError>,
+    ) -> Result<T, DeError> {
+        convert(self.value.clone()).map_err(|e| DeError::from_config(e, expected))
+    }
+
+    /// Fails with `error`, or records it and deserializes a placeholder when collecting errors.
+    fn recover<'de, V: Visitor<'de>>(
+        &self,
+        error: DeError,
+        visitor: V,
+        placeholder: impl FnOnce(Placeholder, V) -> Result<V::Value, DeError>,
+    ) -> Result<V::Value, DeError> {
+        let error = error.at(&self.key);
+        match self.recorder {
+            Some(recorder) => {
+                let attempt = recorder.placeholder(&self.key).unwrap_or(Placeholder(0));
+                recorder.record(error);
+                placeholder(attempt, visitor)
+            }
+            None => Err(error),
+        }
+    }
+}
+
+macro_rules! deserialize_leaf {
+    ($($method:ident => $visit:ident($expected:literal) via $convert:expr,)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            match self.convert($expected, $convert) {
+                Ok(value) => visitor.$visit(value).map_err(|e: DeError| e.at(&self.key)),
+                Err(e) => self.recover(e, visitor, |placeholder, visitor| {
+                    de::Deserializer::$method(placeholder, visitor)
+                }),
+            }
+        }
+    )*};
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident => $visit:ident($ty:ty) via $convert:ident,)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            let value = self.convert(stringify!($ty), Value::$convert).and_then(|value| {
+                <$ty>::try_from(value).map_err(|_| {
+                    DeError::new(
+                        format!("invalid value: integer `{value}`, expected {}", stringify!($ty)),
+                        Some(stringify!($ty).to_owned()),
+                    )
+                })
+            });
+            match value {
+                Ok(value) => visitor.$visit(value).map_err(|e: DeError| e.at(&self.key)),
+                Err(e) => self.recover(e, visitor, |placeholder, visitor| {
+                    de::Deserializer::$method(placeholder, visitor)
+                }),
+            }
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'_> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let key = self.key;
+        match self.value.kind {
+            ValueKind::Nil => visitor.visit_unit(),
+            ValueKind::Boolean(b) => visitor.visit_bool(b),
+            ValueKind::I64(i) => visitor.visit_i64(i),
+            ValueKind::I128(i) => visitor.visit_i128(i),
+            ValueKind::U64(i) => visitor.visit_u64(i),
+            ValueKind::U128(i) => visitor.visit_u128(i),
+            ValueKind::Float(f) => visitor.visit_f64(f),
+            ValueKind::String(s) => visitor.visit_string(s),
+            ValueKind::Array(values) => {
+                visitor.visit_seq(SeqAccess::new(values, key.clone(), self.recorder))
+            }
+            ValueKind::Table(table) => {
+                visitor.visit_map(MapAccess::new(table, key.clone(), self.recorder))
+            }
+        }
+        .map_err(|e: DeError| e.at(&key))
+    }
+
+    deserialize_leaf! {
+        deserialize_bool => visit_bool("bool") via Value::into_bool,
+        deserialize_f32 => visit_f32("f32") via |value| value.into_float().map(|f| f as f32),
+        deserialize_f64 => visit_f64("f64") via Value::into_float,
+        deserialize_str => visit_string("a string") via Value::into_string,
+        deserialize_string => visit_string("a string") via Value::into_string,
+    }
+
+    deserialize_integer! {
+        deserialize_i8 => visit_i8(i8) via into_int,
+        deserialize_i16 => visit_i16(i16) via into_int,
+        deserialize_i32 => visit_i32(i32) via into_int,
+        deserialize_i64 => visit_i64(i64) via into_int,
+        deserialize_i128 => visit_i128(i128) via into_int128,
+        deserialize_u8 => visit_u8(u8) via into_uint,
+        deserialize_u16 => visit_u16(u16) via into_uint,
+        deserialize_u32 => visit_u32(u32) via into_uint,
+        deserialize_u64 => visit_u64(u64) via into_uint,
+        deserialize_u128 => visit_u128(u128) via into_uint128,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let key = self.key.clone();
+        match self.value.kind {
+            ValueKind::Nil => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+        .map_err(|e: DeError| e.at(&key))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let key = self.key.clone();
+        let _scope = (name == secret::NAME).then(|| {
+            SECRET_KEYS.with(|keys| keys.borrow_mut().insert(key.clone()));
+            SecretScope::enter()
+        });
+        visitor
+            .visit_newtype_struct(self)
+            .map_err(|e: DeError| e.at(&key))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let (variant, value) = match self.value.kind.clone() {
+            ValueKind::String(variant) => (variant, None),
+            ValueKind::Table(table) if table.len() == 1 => {
+                let (variant, value) = table.into_iter().next().expect("table has one entry");
+                (variant, Some(value))
+            }
+            _ => {
+                let error = DeError::new(
+                    format!(
+                        "value of enum {name} should be represented by either string or table \
+                         with exactly one key"
+                    ),
+                    Some(format!("enum {name}")),
+                );
+                return self.recover(error, visitor, |placeholder, visitor| {
+                    de::Deserializer::deserialize_enum(placeholder, name, variants, visitor)
+                });
+            }
+        };
+        visitor
+            .visit_enum(EnumAccess {
+                variant,
+                value,
+                key: self.key.clone(),
+                recorder: self.recorder,
+            })
+            .map_err(|e: DeError| e.at(&self.key))
+    }
+
+    serde::forward_to_deserialize_any! {
+        char bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+struct SeqAccess<'r> {
+    elements: std::iter::Enumerate<vec::IntoIter<Value>>,
+    key: String,
+    recorder: Option<&'r Recorder>,
+}
+
+impl<'r> SeqAccess<'r> {
+    fn new(elements: Vec<Value>, key: String, recorder: Option<&'r Recorder>) -> Self {
+        Self {
+            elements: elements.into_iter().enumerate(),
+            key,
+            recorder,
+        }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'_> {
+    type Error = DeError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        let Some((index, value)) = self.elements.next() else {
+            return Ok(None);
+        };
+        let key = format!("{}[{index}]", self.key);
+        if let Some(placeholder) = self
+            .recorder
+            .and_then(|recorder| recorder.placeholder(&key))
+        {
+            return seed
+                .deserialize(placeholder)
+                .map(Some)
+                .map_err(|e| e.at(&key));
+        }
+        seed.deserialize(Deserializer {
+            value,
+            key: key.clone(),
+            recorder: self.recorder,
+        })
+        .map(Some)
+        .map_err(|e| e.at(&key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+struct MapAccess<'r> {
+    elements: VecDeque<(String, Value)>,
+    key: String,
+    recorder: Option<&'r Recorder>,
+}
+
+impl<'r> MapAccess<'r> {
+    fn new(mut table: Map<String, Value>, key: String, recorder: Option<&'r Recorder>) -> Self {
+        // Missing fields that were already reported are handed to the visitor as placeholders.
+        if let Some(recorder) = recorder {
+            for placeholder in recorder.placeholders.borrow().keys() {
+                let field = match key.as_str() {
+                    "" => Some(placeholder.as_str()),
+                    key => placeholder
+                        .strip_prefix(key)
+                        .and_then(|rest| rest.strip_prefix('.')),
+                };
+                if let Some(field) = field.filter(|field| !field.contains(['.', '['])) {
+                    table
+                        .entry(field.to_owned())
+                        .or_insert_with(|| Value::new(None, ValueKind::Nil));
+                }
+            }
+        }
+
+        // Keys that reject every placeholder are left out, and the tables leading to them are
+        // visited last, so that everything else is checked before deserialization gives up.
+        let mut elements: Vec<_> = table
+            .into_iter()
+            .map(|(field, value)| {
+                let last = recorder
+                    .is_some_and(|recorder| recorder.leads_to_deferred(&join(&key, &field)));
+                (last, field, value)
+            })
+            .filter(|(_, field, _)| {
+                !recorder.is_some_and(|recorder| recorder.is_deferred(&join(&key, field)))
+            })
+            .collect();
+        elements.sort_by(|(a, a_field, _), (b, b_field, _)| (a, a_field).cmp(&(b, b_field)));
+        let elements = elements
+            .into_iter()
+            .map(|(_, field, value)| (field, value))
+            .collect();
+        Self {
+            elements,
+            key,
+            recorder,
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'_> {
+    type Error = DeError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        self.elements
+            .front()
+            .map(|(key, _)| seed.deserialize(key.clone().into_deserializer()))
+            .transpose()
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let (key, value) = self
+            .elements
+            .pop_front()
+            .expect("next_value_seed called after next_key_seed");
+        let key = join(&self.key, &key);
+        if let Some(placeholder) = self
+            .recorder
+            .and_then(|recorder| recorder.placeholder(&key))
+        {
+            return seed.deserialize(placeholder).map_err(|e| e.at(&key));
+        }
+        // Errors raised after the value was read, such as by `try_from`, belong to this key.
+        seed.deserialize(Deserializer {
+            value,
+            key: key.clone(),
+            recorder: self.recorder,
+        })
+        .map_err(|e| e.at(&key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+struct EnumAccess<'r> {
+    variant: String,
+    value: Option<Value>,
+    key: String,
+    recorder: Option<&'r Recorder>,
+}
+
+impl<'r> EnumAccess<'r> {
+    fn content(self) -> Result<Deserializer<'r>, DeError> {
+        match self.value {
+            Some(value) => Ok(Deserializer {
+                value,
+                key: join(&self.key, &self.variant),
+                recorder: self.recorder,
+            }),
+            None => Err(DeError::new(
+                format!("expected a table for enum variant `{}`", self.variant),
+                None,
+            )
+            .at(&self.key)),
+        }
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'_> {
+    type Error = DeError;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant = seed.deserialize(self.variant.clone().into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for EnumAccess<'_> {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self.content()?)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_seq(self.content()?, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_map(self.content()?, visitor)
+    }
+}
+
+/// Stands in for a value that has already been reported as invalid, producing the "zero" value
+/// of whatever type is requested. Later attempts produce other common values for types that
+/// reject the zero value.
+#[derive(Clone, Copy)]
+struct Placeholder(usize);
+
+impl Placeholder {
+    const STRINGS: &'static [&'static str] =
+        &["", "0", "0.0.0.0", "0.0.0.0:0", "http://localhost/"];
+    const ATTEMPTS: usize = Self::STRINGS.len();
+
+    fn pick<T: Copy>(self, candidates: &[T]) -> T {
+        candidates[self.0.min(candidates.len() - 1)]
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Placeholder {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_bool(self.pick(&[false, true]))
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i8(self.pick(&[0, 1]))
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i16(self.pick(&[0, 1]))
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i32(self.pick(&[0, 1]))
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i64(self.pick(&[0, 1]))
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i128(self.pick(&[0, 1]))
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u8(self.pick(&[0, 1]))
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u16(self.pick(&[0, 1]))
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u32(self.pick(&[0, 1]))
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u64(self.pick(&[0, 1]))
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u128(self.pick(&[0, 1]))
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_f32(self.pick(&[0.0, 1.0]))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_f64(self.pick(&[0.0, 1.0]))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_char('\0')
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(self.pick(Self::STRINGS))
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_string(self.pick(Self::STRINGS).to_owned())
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_bytes(&[])
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_byte_buf(Vec::new())
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_none()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(PlaceholderSeq(0, self))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(PlaceholderSeq(len, self))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(PlaceholderSeq(len, self))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(PlaceholderMap([].iter(), self))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(PlaceholderMap(fields.iter(), self))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match variants.first() {
+            Some(variant) => visitor.visit_enum(PlaceholderEnum(variant, self)),
+            None => Err(de::Error::custom(format!("enum {name} has no variants"))),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str("")
+    }
+
+    serde::forward_to_deserialize_any! {
+        unit unit_struct ignored_any
+    }
+}
+
+struct PlaceholderSeq(usize, Placeholder);
+
+impl<'de> de::SeqAccess<'de> for PlaceholderSeq {
+    type Error = DeError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        if self.0 == 0 {
+            return Ok(None);
+        }
+        self.0 -= 1;
+        seed.deserialize(self.1).map(Some)
+    }
+}
+
+struct PlaceholderMap(std::slice::Iter<'static, &'static str>, Placeholder);
+
+impl<'de> de::MapAccess<'de> for PlaceholderMap {
+    type Error = DeError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        self.0
+            .next()
+            .map(|field| seed.deserialize((*field).into_deserializer()))
+            .transpose()
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        seed.deserialize(self.1)
+    }
+}
+
+struct PlaceholderEnum(&'static str, Placeholder);
+
+impl<'de> de::EnumAccess<'de> for PlaceholderEnum {
+    type Error = DeError;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant = seed.deserialize(self.0.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for PlaceholderEnum {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self.1)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(PlaceholderSeq(len, self.1))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(PlaceholderMap(fields.iter(), self.1))
+    }
+}
@@ -0,0 +1,168 @@
+use crate::Error;
+use age::{Decryptor, Identity, IdentityFile};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use config::{Map, Value, ValueKind};
+use std::{io::Read, path::PathBuf};
+
+const PREFIX: &str = "ENC[age,";
+const SUFFIX: &str = "]";
+
+/// Where age identities are loaded from, lazily, once the first encrypted value is found.
+pub(crate) struct Keys {
+    file: Option<PathBuf>,
+    variable: Option<String>,
+    identities: Option<Vec<Box<dyn Identity>>>,
+}
+
+impl Keys {
+    pub(crate) fn new(file: Option<PathBuf>, variable: Option<String>) -> Self {
+        Self {
+            file,
+            variable,
+            identities: None,
+        }
+    }
+
+    fn identities(&mut self) -> Result<&[Box<dyn Identity>], Error> {
+        if self.identities.is_none() {
+            let mut keys = String::new();
+            if let Some(file) = &self.file {
+                keys += &std::fs::read_to_string(file)
+                    .map_err(|e| Error::DecryptionKey(Box::new(e)))?;
+                keys.push('\n');
+            }
+            if let Some(variable) = &self.variable {
+                // An unset variable only matters when no other key decrypts the value.
+                keys += &std::env::var(variable).unwrap_or_default();
+            }
+            let identities = IdentityFile::from_buffer(keys.as_bytes())
+                .map_err(|e| Error::DecryptionKey(Box::new(e)))?
+                .into_identities()
+                .map_err(|e| Error::DecryptionKey(Box::new(e)))?;
+            if identities.is_empty() {
+                return Err(Error::DecryptionKey(
+                    "No age identity found, set a key file or a key variable".into(),
+                ));
+            }
+            self.identities = Some(identities);
+        }
+        Ok(self.identities.as_deref().unwrap_or_default())
+    }
+}
+
+/// Replaces every `ENC[age,<base64 ciphertext>]` string in `tree` with its plaintext, returning
+/// the key paths of the decrypted values.
+pub(crate) fn decrypt(
+    tree: &mut Map<String, Value>,
+    keys: &mut Keys,
+) -> Result<Vec<String>, Error> {
+    let mut entries: Vec<_> = tree.iter_mut().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    let mut decrypted = Vec::new();
+    for (key, value) in entries {
+        decrypt_value(key, value, keys, &mut decrypted)?;
+    }
+    Ok(decrypted)
+}
+
+fn decrypt_value(
+    key: &str,
+    value: &mut Value,
+    keys: &mut Keys,
+    decrypted: &mut Vec<String>,
+) -> Result<(), Error> {
+    match &mut value.kind {
+        ValueKind::String(text) => {
+            let Some(ciphertext) = text
+                .strip_prefix(PREFIX)
+                .and_then(|text| text.strip_suffix(SUFFIX))
+            else {
+                return Ok(());
+            };
+            let failure =
+                |e: Box<dyn std::error::Error + Send + Sync>| Error::Decryption(key.to_owned(), e);
+            let ciphertext = STANDARD
+                .decode(ciphertext.trim())
+                .map_err(|e| failure(Box::new(e)))?;
+            let identities = keys.identities()?;
+            let mut plaintext = String::new();
+            Decryptor::new_buffered(ciphertext.as_slice())
+                .and_then(|decryptor| {
+                    decryptor.decrypt(identities.iter().map(|identity| identity.as_ref()))
+                })
+                .map_err(|e| failure(Box::new(e)))?
+                .read_to_string(&mut plaintext)
+                .map_err(|e| failure(Box::new(e)))?;
+            *text = plaintext;
+            decrypted.push(key.to_owned());
+        }
+        ValueKind::Table(table) => {
+            for (child, value) in table.iter_mut() {
+                decrypt_value(&format!("{key}.{child}"), value, keys, decrypted)?;
+            }
+        }
+        ValueKind::Array(array) => {
+            for (index, value) in array.iter_mut().enumerate() {
+                decrypt_value(&format!("{key}[{index}]"), value, keys, decrypted)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merge;
+    use age::{secrecy::ExposeSecret, x25519};
+
+    fn encrypt(recipient: &x25519::Recipient, plaintext: &str) -> Value {
+        let ciphertext = age::encrypt(recipient, plaintext.as_bytes()).unwrap();
+        Value::new(
+            None,
+            format!("{PREFIX}{}{SUFFIX}", STANDARD.encode(ciphertext)),
+        )
+    }
+
+    #[test]
+    fn decrypt_values() {
+        let identity = x25519::Identity::generate();
+        let recipient = identity.to_public();
+        std::env::set_var("DECRYPT_KEY", identity.to_string().expose_secret());
+
+        let mut tree = Map::new();
+        merge::insert(
+            &mut tree,
+            "database.password",
+            encrypt(&recipient, "hunter2"),
+        );
+        merge::insert(&mut tree, "database.user", Value::new(None, "admin"));
+        let mut keys = Keys::new(None, Some(String::from("DECRYPT_KEY")));
+        assert_eq!(
+            decrypt(&mut tree, &mut keys).unwrap(),
+            ["database.password"]
+        );
+        let mut expected = Map::new();
+        merge::insert(
+            &mut expected,
+            "database.password",
+            Value::new(None, "hunter2"),
+        );
+        merge::insert(&mut expected, "database.user", Value::new(None, "admin"));
+        assert_eq!(tree, expected);
+
+        let other = x25519::Identity::generate().to_public();
+        let mut tree = Map::new();
+        merge::insert(&mut tree, "database.password", encrypt(&other, "hunter2"));
+        let err = decrypt(&mut tree, &mut keys).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to decrypt value at `database.password`"
+        );
+
+        let mut keys = Keys::new(None, Some(String::from("MISSING_DECRYPT_KEY")));
+        let err = decrypt(&mut tree, &mut keys).unwrap_err();
+        assert!(matches!(err, Error::DecryptionKey(_)));
+    }
+}
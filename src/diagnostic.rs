@@ -0,0 +1,90 @@
+use crate::{de::DeError, Location, Origin, Provenance};
+use std::fmt;
+
+/// A value that could not be deserialized, with the key path and the source that supplied it.
+///
+/// Renders as a compiler-style diagnostic quoting the offending line when the value came from a
+/// file with a known location:
+///
+/// ```text
+/// invalid type: string "abc", expected u16 at `bar.baz`
+///  --> config/production.toml:2:1
+///   |
+/// 2 | baz = "abc"
+///   | ^^^^^^^^^^^
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// Dotted key path, with `[index]` for array elements. Empty for the root.
+    pub key: String,
+    pub message: String,
+    /// The expected type, e.g. `u16`, when known.
+    pub expected: Option<String>,
+    /// The source of the offending value, `None` for missing values.
+    pub origin: Option<Origin>,
+    /// The source line of `origin`.
+    pub snippet: Option<String>,
+}
+
+impl FieldError {
+    /// Errors about [`Secret`](crate::Secret)s neither quote the value nor its source line.
+    pub(crate) fn new(error: DeError, provenance: &Provenance) -> Self {
+        let key = error.key.unwrap_or_default();
+        let origin = provenance.closest(&key).map(|trace| trace.origin.clone());
+        let snippet = match &origin {
+            Some(Origin::File {
+                path,
+                location: Some(Location { line, .. }),
+            }) if !error.secret => std::fs::read_to_string(path)
+                .ok()
+                .and_then(|contents| contents.lines().nth(line - 1).map(ToOwned::to_owned)),
+            _ => None,
+        };
+        let message = match (&error.expected, error.secret) {
+            (_, false) => error.message,
+            (Some(expected), true) => format!("invalid secret value, expected {expected}"),
+            (None, true) => String::from("invalid secret value"),
+        };
+
+        Self {
+            key,
+            message,
+            expected: error.expected,
+            origin,
+            snippet,
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if !self.key.is_empty() {
+            write!(f, " at `{}`", self.key)?;
+        }
+        let Some(origin) = &self.origin else {
+            return Ok(());
+        };
+        write!(f, "\n --> {origin}")?;
+
+        if let (
+            Origin::File {
+                location: Some(Location { line, column }),
+                ..
+            },
+            Some(snippet),
+        ) = (origin, &self.snippet)
+        {
+            let gutter = " ".repeat(line.to_string().len());
+            let underline = "^".repeat(snippet.trim_end().len().saturating_sub(column - 1).max(1));
+            write!(
+                f,
+                "\n{gutter} |\n{line} | {snippet}\n{gutter} | {}{underline}",
+                " ".repeat(column - 1)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FieldError {}
@@ -0,0 +1,189 @@
+use config::{Map, Value, ValueKind};
+use std::{collections::BTreeMap, fmt};
+
+/// How a single value differs between two loads.
+///
+/// The [`Debug`](fmt::Debug) implementation redacts the values, which may be secrets.
+#[derive(Clone, PartialEq)]
+pub enum Change {
+    Added(Value),
+    Removed(Value),
+    Changed { old: Value, new: Value },
+}
+
+/// Differences between two merged configs, keyed by dotted key path such as `bar.baz`.
+///
+/// The [`Display`](fmt::Display) implementation renders one line per key, prefixed with `+`,
+/// `-` or `~`. Values are left out so that secrets don't end up in logs, use [`get`](Self::get)
+/// to inspect them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diff {
+    changes: BTreeMap<String, Change>,
+}
+
+impl Diff {
+    pub(crate) fn new(old: &Map<String, Value>, new: &Map<String, Value>) -> Self {
+        let mut old = flatten(old);
+        let mut changes = BTreeMap::new();
+        for (key, new) in flatten(new) {
+            match old.remove(&key) {
+                Some(old) if same(&old.kind, &new.kind) => {}
+                Some(old) => {
+                    changes.insert(key, Change::Changed { old, new });
+                }
+                None => {
+                    changes.insert(key, Change::Added(new));
+                }
+            }
+        }
+        changes.extend(
+            old.into_iter()
+                .map(|(key, old)| (key, Change::Removed(old))),
+        );
+        Self { changes }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Change> {
+        self.changes.get(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Change)> {
+        self.changes
+            .iter()
+            .map(|(key, change)| (key.as_str(), change))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Whether `key` or anything below it changed, e.g. `touches("database")` is true when
+    /// `database.url` changed.
+    pub fn touches(&self, key: &str) -> bool {
+        let descendants = format!("{key}.");
+        self.changes
+            .keys()
+            .any(|other| other == key || other.starts_with(&descendants))
+    }
+}
+
+impl fmt::Debug for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added(_) => f.debug_tuple("Added").field(&Redacted).finish(),
+            Self::Removed(_) => f.debug_tuple("Removed").field(&Redacted).finish(),
+            Self::Changed { .. } => f
+                .debug_struct("Changed")
+                .field("old", &Redacted)
+                .field("new", &Redacted)
+                .finish(),
+        }
+    }
+}
+
+/// Stands in for a value in [`Change`]'s debug output.
+struct Redacted;
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, change) in self.iter() {
+            let marker = match change {
+                Change::Added(_) => '+',
+                Change::Removed(_) => '-',
+                Change::Changed { .. } => '~',
+            };
+            writeln!(f, "{marker} {key}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether two values deserialize the same, e.g. `5432` from a file and `"5432"` from an
+/// environment variable.
+fn same(old: &ValueKind, new: &ValueKind) -> bool {
+    match (old, new) {
+        (ValueKind::Array(old), ValueKind::Array(new)) => {
+            old.len() == new.len()
+                && old
+                    .iter()
+                    .zip(new)
+                    .all(|(old, new)| same(&old.kind, &new.kind))
+        }
+        (ValueKind::Table(old), ValueKind::Table(new)) => {
+            old.len() == new.len()
+                && old
+                    .iter()
+                    .all(|(key, old)| new.get(key).is_some_and(|new| same(&old.kind, &new.kind)))
+        }
+        (ValueKind::Nil, ValueKind::Nil) => true,
+        (ValueKind::Array(_) | ValueKind::Table(_) | ValueKind::Nil, _)
+        | (_, ValueKind::Array(_) | ValueKind::Table(_) | ValueKind::Nil) => false,
+        (old, new) => old.to_string() == new.to_string(),
+    }
+}
+
+/// Every leaf by dotted key path, empty tables included.
+fn flatten(table: &Map<String, Value>) -> BTreeMap<String, Value> {
+    let mut leaves = BTreeMap::new();
+    for (key, value) in table {
+        match &value.kind {
+            ValueKind::Table(table) if !table.is_empty() => leaves.extend(
+                flatten(table)
+                    .into_iter()
+                    .map(|(leaf, value)| (format!("{key}.{leaf}"), value)),
+            ),
+            _ => {
+                leaves.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    leaves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merge;
+
+    #[test]
+    fn added_removed_changed() {
+        let mut old = Map::new();
+        merge::insert(&mut old, "database.url", Value::new(None, "postgres://a"));
+        merge::insert(&mut old, "database.pool", Value::new(None, 4));
+        merge::insert(&mut old, "cache.ttl", Value::new(None, 60));
+        let mut new = Map::new();
+        merge::insert(&mut new, "database.url", Value::new(None, "postgres://b"));
+        merge::insert(&mut new, "database.pool", Value::new(None, 4));
+        merge::insert(&mut new, "http.port", Value::new(None, 8080));
+
+        let diff = Diff::new(&old, &new);
+        assert_eq!(
+            diff.get("database.url"),
+            Some(&Change::Changed {
+                old: Value::new(None, "postgres://a"),
+                new: Value::new(None, "postgres://b"),
+            })
+        );
+        assert_eq!(diff.get("database.pool"), None);
+        assert!(diff.touches("database"));
+        assert!(!diff.touches("data"));
+        assert_eq!(
+            diff.to_string(),
+            "- cache.ttl\n~ database.url\n+ http.port\n"
+        );
+        assert!(!format!("{diff:?}").contains("postgres"));
+        assert!(Diff::new(&new, &new).is_empty());
+
+        let mut env = Map::new();
+        merge::insert(&mut env, "database.url", Value::new(None, "postgres://b"));
+        merge::insert(&mut env, "database.pool", Value::new(None, "4"));
+        merge::insert(&mut env, "http.port", Value::new(None, "8080"));
+        assert!(Diff::new(&new, &env).is_empty());
+    }
+}
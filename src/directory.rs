@@ -0,0 +1,57 @@
+use crate::{
+    merge::{self, Collected},
+    Error, Layer, Origin,
+};
+use config::{Map, Value};
+use std::{io, path::Path};
+
+/// Collects a directory with one file per value, such as mounted Docker or Kubernetes
+/// secrets. File names are key paths with `separator` between segments, e.g.
+/// `database__password` → `database.password`, and contents lose their trailing newlines.
+///
+/// Only files starting with `prefix` are collected, with the prefix stripped. Hidden files and
+/// subdirectories are skipped, as is a missing `root`.
+pub(crate) fn collect(
+    root: &Path,
+    prefix: &str,
+    separator: &str,
+) -> Result<Option<Collected>, Error> {
+    let access = |e| Error::DirectoryAccess(root.to_path_buf(), e);
+    let prefix = prefix.to_lowercase();
+    let entries = match std::fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(access(e)),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(access)?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !name.starts_with('.') && name.to_lowercase().starts_with(&prefix) && path.is_file() {
+            files.push((name.to_owned(), path));
+        }
+    }
+    files.sort();
+
+    let mut values = Map::new();
+    let mut origins = Vec::new();
+    for (name, path) in files {
+        let contents =
+            std::fs::read_to_string(&path).map_err(|e| Error::DirectoryAccess(path.clone(), e))?;
+        let mut key = name.to_lowercase()[prefix.len()..].to_owned();
+        if !separator.is_empty() {
+            key = key.replace(separator, ".");
+        }
+        let value = contents.trim_end_matches(['\n', '\r']);
+        merge::insert(&mut values, &key, Value::new(Some(&name), value));
+        origins.push((key, Origin::file(&path, None)));
+    }
+
+    Ok(Some(Collected {
+        layer: Layer::Directory(root.to_path_buf()),
+        values,
+        origins,
+    }))
+}
@@ -0,0 +1,24 @@
+use crate::{Error, Origin};
+use std::path::Path;
+
+/// Reads `.env` and then `.env.<environment>` from `directory`, skipping missing files. Later
+/// assignments override earlier ones.
+pub(crate) fn read(
+    directory: &Path,
+    environment: &str,
+) -> Result<Vec<(String, String, Origin)>, Error> {
+    let mut variables = Vec::new();
+    for name in [String::from(".env"), format!(".env.{environment}")] {
+        let path = directory.join(name);
+        let lines = match dotenvy::from_path_iter(&path) {
+            Ok(lines) => lines,
+            Err(e) if e.not_found() => continue,
+            Err(e) => return Err(Error::DotenvParsing(path, e)),
+        };
+        for line in lines {
+            let (name, value) = line.map_err(|e| Error::DotenvParsing(path.clone(), e))?;
+            variables.push((name, value, Origin::file(&path, None)));
+        }
+    }
+    Ok(variables)
+}
@@ -0,0 +1,184 @@
+use crate::{Config, Error};
+use std::{collections::HashMap, fmt, path::PathBuf};
+use strum::{Display, EnumString, EnumVariantNames, VariantNames};
+
+/// The environments accepted when [`ConfigBuilder::environments`](crate::ConfigBuilder::environments)
+/// is not set.
+#[derive(EnumString, EnumVariantNames, Display, Default)]
+#[strum(serialize_all = "snake_case")]
+pub enum Environment {
+    #[default]
+    Local,
+    Production,
+}
+
+/// A place the environment name is read from, see
+/// [`ConfigBuilder::environment_detector`](crate::ConfigBuilder::environment_detector).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvironmentDetector {
+    /// Value of an environment variable, e.g. `APP_ENV`.
+    Variable(String),
+    /// Value of a command line flag, given as `--env production` or `--env=production`.
+    Argument(String),
+    /// Trimmed contents of a marker file, e.g. `/etc/app-environment`. A missing file is skipped.
+    File(PathBuf),
+    /// `debug` in debug builds and `release` otherwise. Always detects an environment.
+    BuildProfile { debug: String, release: String },
+}
+
+impl EnvironmentDetector {
+    fn detect(&self) -> Result<Option<String>, Error> {
+        match self {
+            Self::Variable(name) => Ok(std::env::var(name).ok()),
+            Self::Argument(flag) => {
+                let mut args = std::env::args().skip(1);
+                while let Some(arg) = args.next() {
+                    if arg == *flag {
+                        return Ok(args.next());
+                    }
+                    if let Some(value) = arg
+                        .strip_prefix(flag.as_str())
+                        .and_then(|rest| rest.strip_prefix('='))
+                    {
+                        return Ok(Some(value.to_owned()));
+                    }
+                }
+                Ok(None)
+            }
+            Self::File(path) => match std::fs::read_to_string(path) {
+                Ok(contents) => Ok(Some(contents.trim().to_owned())),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(Error::EnvironmentFileAccess(path.clone(), e)),
+            },
+            Self::BuildProfile { debug, release } => Ok(Some(if cfg!(debug_assertions) {
+                debug.clone()
+            } else {
+                release.clone()
+            })),
+        }
+    }
+}
+
+impl fmt::Display for EnvironmentDetector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Variable(name) => write!(f, "environment variable `{name}`"),
+            Self::Argument(flag) => write!(f, "command line flag `{flag}`"),
+            Self::File(path) => write!(f, "file `{}`", path.display()),
+            Self::BuildProfile { .. } => write!(f, "build profile"),
+        }
+    }
+}
+
+/// The outcome of environment detection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    /// One of [`Config::environments`].
+    pub environment: String,
+    /// The detector that chose `environment`, `None` when `default_environment` was used.
+    pub detector: Option<EnvironmentDetector>,
+}
+
+impl Config {
+    /// Runs the detector chain, falling back to `environment_variable_name` when no detectors
+    /// are configured.
+    pub(crate) fn detect_environment(&self) -> Result<Detection, Error> {
+        let detectors = if self.environment_detectors.is_empty() {
+            vec![EnvironmentDetector::Variable(
+                self.environment_variable_name.clone(),
+            )]
+        } else {
+            self.environment_detectors.clone()
+        };
+
+        for detector in detectors.iter() {
+            let Some(value) = detector.detect()? else {
+                continue;
+            };
+            let environment = resolve(
+                &value,
+                &self.environments,
+                &self.environment_aliases,
+                self.environment_case_insensitive,
+            )
+            .ok_or_else(|| Error::EnvironmentVariableParsing(value, self.environments.clone()))?;
+
+            return Ok(Detection {
+                environment: environment.to_owned(),
+                detector: Some(detector.clone()),
+            });
+        }
+
+        let strict = self.strict_environment
+            || (self.strict_environment_in_release && cfg!(not(debug_assertions)));
+        if strict {
+            return Err(Error::EnvironmentMissing(detectors));
+        }
+
+        Ok(Detection {
+            environment: self.default_environment.clone(),
+            detector: None,
+        })
+    }
+}
+
+pub(crate) fn default_environments() -> Vec<String> {
+    Environment::VARIANTS
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Maps a raw environment value onto one of `environments`, either directly or through one of
+/// `aliases`.
+pub(crate) fn resolve<'a>(
+    value: &str,
+    environments: &'a [String],
+    aliases: &'a HashMap<String, String>,
+    case_insensitive: bool,
+) -> Option<&'a str> {
+    let matches = |candidate: &str| {
+        if case_insensitive {
+            candidate.eq_ignore_ascii_case(value)
+        } else {
+            candidate == value
+        }
+    };
+
+    environments
+        .iter()
+        .find(|environment| matches(environment))
+        .or_else(|| {
+            aliases
+                .iter()
+                .find(|(alias, _)| matches(alias))
+                .map(|(_, environment)| environment)
+        })
+        .map(String::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_aliases() {
+        let environments = default_environments();
+        let aliases = HashMap::from([
+            (String::from("prod"), String::from("production")),
+            (String::from("dev"), String::from("local")),
+        ]);
+
+        assert_eq!(
+            resolve("prod", &environments, &aliases, false),
+            Some("production")
+        );
+        assert_eq!(resolve("Production", &environments, &aliases, false), None);
+        assert_eq!(
+            resolve("Production", &environments, &aliases, true),
+            Some("production")
+        );
+        assert_eq!(resolve("DEV", &environments, &aliases, true), Some("local"));
+        assert_eq!(resolve("qa", &environments, &aliases, true), None);
+    }
+}
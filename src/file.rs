@@ -0,0 +1,247 @@
+use crate::{
+    interpolate,
+    merge::{self, Collected},
+    Error, Layer, Location, Origin,
+};
+use config::{ConfigError, FileFormat, Format};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Formats probed for a file stem, in lookup order, with their extensions. TOML is always
+/// available, the others are enabled by the cargo feature of the same name.
+const FORMATS: &[(FileFormat, &[&str])] = &[
+    (FileFormat::Toml, &["toml"]),
+    #[cfg(feature = "json")]
+    (FileFormat::Json, &["json"]),
+    #[cfg(feature = "yaml")]
+    (FileFormat::Yaml, &["yaml", "yml"]),
+    #[cfg(feature = "ini")]
+    (FileFormat::Ini, &["ini"]),
+    #[cfg(feature = "ron")]
+    (FileFormat::Ron, &["ron"]),
+    #[cfg(feature = "json5")]
+    (FileFormat::Json5, &["json5"]),
+];
+
+/// Finds the file `stem.<extension>` among the supported extensions. When several exist, the
+/// first extension in `precedence` wins, otherwise the lookup is ambiguous.
+pub(crate) fn find(
+    stem: &Path,
+    precedence: &[String],
+) -> Result<Option<(PathBuf, FileFormat)>, Error> {
+    let mut candidates: Vec<_> = FORMATS
+        .iter()
+        .flat_map(|(format, extensions)| {
+            extensions.iter().filter_map(|extension| {
+                let mut path = stem.as_os_str().to_owned();
+                path.push(".");
+                path.push(extension);
+                let path = PathBuf::from(path);
+                path.is_file().then_some((*extension, path, *format))
+            })
+        })
+        .collect();
+
+    let preferred = precedence.iter().find_map(|preferred| {
+        candidates
+            .iter()
+            .position(|(extension, ..)| extension == preferred)
+    });
+    if preferred.is_none() && candidates.len() > 1 {
+        return Err(Error::AmbiguousConfigFile(
+            candidates.into_iter().map(|(_, path, _)| path).collect(),
+        ));
+    }
+    Ok((!candidates.is_empty()).then(|| {
+        let (_, path, format) = candidates.swap_remove(preferred.unwrap_or_default());
+        (path, format)
+    }))
+}
+
+/// Whether `extension` belongs to a format enabled by cargo features.
+pub(crate) fn is_supported(extension: &str) -> bool {
+    FORMATS
+        .iter()
+        .any(|(_, extensions)| extensions.contains(&extension))
+}
+
+/// Loads the file at `path`, expanding `${VAR}` placeholders from the process environment
+/// when `interpolate` is set. `keep_escapes` leaves `$${` for reference resolution to unescape.
+pub(crate) fn load(
+    path: &Path,
+    format: FileFormat,
+    interpolate: bool,
+    keep_escapes: bool,
+) -> Result<Collected, Error> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| Error::ComposeSchema(ConfigError::Foreign(Box::new(e))))?;
+    let uri = path.display().to_string();
+    let mut values = format.parse(Some(&uri), &contents).map_err(|cause| {
+        Error::ComposeSchema(ConfigError::FileParse {
+            uri: Some(uri),
+            cause,
+        })
+    })?;
+
+    let locations = locate(&contents, format);
+    let origin = |key: &str| {
+        // Keys of inline tables and arrays are reported at their closest located parent.
+        let key = key.split('[').next().unwrap_or(key);
+        let location = std::iter::successors(Some(key), |key| {
+            key.rsplit_once('.').map(|(parent, _)| parent)
+        })
+        .find_map(|key| locations.get(key).copied());
+        Origin::file(path, location)
+    };
+
+    if interpolate {
+        interpolate::interpolate(
+            &mut values,
+            keep_escapes,
+            &mut interpolate::environment_variable,
+        )
+        .map_err(|(key, message)| {
+            let origin = origin(&key);
+            Error::Interpolation(key, Some(origin), message)
+        })?;
+    }
+
+    let origins = merge::leaves(&values)
+        .into_iter()
+        .map(|key| {
+            let origin = origin(&key);
+            (key, origin)
+        })
+        .collect();
+
+    Ok(Collected {
+        layer: Layer::File(path.to_path_buf()),
+        values,
+        origins,
+    })
+}
+
+/// Best-effort key path locations, only TOML is supported.
+fn locate(contents: &str, format: FileFormat) -> HashMap<String, Location> {
+    match format {
+        FileFormat::Toml => locate_toml(contents),
+        // Unreachable when no other format feature is enabled.
+        #[allow(unreachable_patterns)]
+        _ => HashMap::new(),
+    }
+}
+
+fn locate_toml(contents: &str) -> HashMap<String, Location> {
+    let mut locations = HashMap::new();
+    let mut table = String::new();
+    let mut multiline_string: Option<&str> = None;
+    let mut depth = 0;
+
+    for (index, line) in contents.lines().enumerate() {
+        if let Some(delimiter) = multiline_string {
+            if line.contains(delimiter) {
+                multiline_string = None;
+            }
+            continue;
+        }
+        if depth > 0 {
+            depth += nesting(line);
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        let location = Location {
+            line: index + 1,
+            column: line.len() - trimmed.len() + 1,
+        };
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = trimmed.strip_prefix('[') {
+            let header = header.trim_start_matches('[');
+            let header = header.split(']').next().unwrap_or_default();
+            table = key_path(header);
+            locations.entry(table.clone()).or_insert(location);
+        } else if let Some((key, value)) = trimmed.split_once('=') {
+            let key = key_path(key);
+            let key = if table.is_empty() {
+                key
+            } else {
+                format!("{table}.{key}")
+            };
+            locations.insert(key, location);
+
+            let value = value.trim();
+            for delimiter in [r#"""""#, "'''"] {
+                if value.starts_with(delimiter) && value.matches(delimiter).count() == 1 {
+                    multiline_string = Some(delimiter);
+                }
+            }
+            depth = nesting(value).max(0);
+        }
+    }
+
+    locations
+}
+
+/// Dotted key path of a possibly quoted TOML key.
+fn key_path(key: &str) -> String {
+    key.split('.')
+        .map(|part| part.trim().trim_matches(|c| c == '"' || c == '\''))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Change in array and inline table nesting over `line`, ignoring strings and comments.
+fn nesting(line: &str) -> i32 {
+    let mut depth = 0;
+    let mut quote = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => break,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locate_toml_keys() {
+        let contents = "\
+title = \"cfgio\"
+
+[bar]
+baz = 1
+  list = [
+    1,
+  ]
+description = \"\"\"
+fake = 1
+\"\"\"
+
+[bar.\"quoted\"]
+qux = { a = 1 }
+";
+        let locations = locate_toml(contents);
+        let at = |line, column| Some(Location { line, column });
+        assert_eq!(locations.get("title").copied(), at(1, 1));
+        assert_eq!(locations.get("bar").copied(), at(3, 1));
+        assert_eq!(locations.get("bar.baz").copied(), at(4, 1));
+        assert_eq!(locations.get("bar.list").copied(), at(5, 3));
+        assert_eq!(locations.get("bar.description").copied(), at(8, 1));
+        assert_eq!(locations.get("fake").copied(), None);
+        assert_eq!(locations.get("bar.quoted.qux").copied(), at(13, 1));
+    }
+}
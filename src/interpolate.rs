@@ -0,0 +1,160 @@
+use config::{Map, Value, ValueKind};
+
+/// What a `${name}` placeholder resolves to.
+pub(crate) enum Lookup {
+    Found(String),
+    Missing,
+    /// Not handled by this lookup, the placeholder is kept as is.
+    Skip,
+}
+
+/// Expands the placeholders of every string in `tree`, failing with the key path of the value
+/// and a message. `lookup` is called with the key path of the value and the placeholder name.
+/// `keep_escapes` leaves `$${` as is for a later pass to unescape.
+pub(crate) fn interpolate(
+    tree: &mut Map<String, Value>,
+    keep_escapes: bool,
+    lookup: &mut impl FnMut(&str, &str) -> Lookup,
+) -> Result<(), (String, String)> {
+    let mut entries: Vec<_> = tree.iter_mut().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    for (key, value) in entries {
+        interpolate_value(key, value, keep_escapes, lookup)?;
+    }
+    Ok(())
+}
+
+fn interpolate_value(
+    key: &str,
+    value: &mut Value,
+    keep_escapes: bool,
+    lookup: &mut impl FnMut(&str, &str) -> Lookup,
+) -> Result<(), (String, String)> {
+    match &mut value.kind {
+        ValueKind::String(text) => {
+            *text = expand(text, keep_escapes, &mut |name| lookup(key, name))
+                .map_err(|message| (key.to_owned(), message))?;
+        }
+        ValueKind::Table(table) => {
+            let mut entries: Vec<_> = table.iter_mut().collect();
+            entries.sort_by_key(|(child, _)| *child);
+            for (child, value) in entries {
+                interpolate_value(&format!("{key}.{child}"), value, keep_escapes, lookup)?;
+            }
+        }
+        ValueKind::Array(array) => {
+            for (index, value) in array.iter_mut().enumerate() {
+                interpolate_value(&format!("{key}[{index}]"), value, keep_escapes, lookup)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Expands `${name}`, `${name:-default}` and `${name:?message}` in `text`. A default is used
+/// and a message reported when `name` is missing or empty, a bare `${name}` must be present.
+/// `$${` is a literal `${`, or kept as is with `keep_escapes`.
+pub(crate) fn expand(
+    text: &str,
+    keep_escapes: bool,
+    lookup: &mut impl FnMut(&str) -> Lookup,
+) -> Result<String, String> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            let end = if keep_escapes { start } else { start - 1 };
+            expanded.push_str(&rest[..end]);
+            expanded.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        expanded.push_str(&rest[..start]);
+        let Some(length) = rest[start..].find('}') else {
+            return Err(format!("unterminated placeholder `{}`", &rest[start..]));
+        };
+        let placeholder = &rest[start..start + length + 1];
+        let inner = &placeholder[2..placeholder.len() - 1];
+        rest = &rest[start + length + 1..];
+
+        // Only the first `:` separates the name, defaults and messages may contain `:-` or `:?`.
+        let (name, fallback) = match inner.split_once(':') {
+            Some((name, fallback)) if fallback.starts_with(['-', '?']) => {
+                (name, Some(&inner[name.len()..]))
+            }
+            _ => (inner, None),
+        };
+        match (lookup(name), fallback) {
+            (Lookup::Skip, _) => expanded.push_str(placeholder),
+            (Lookup::Found(value), None) => expanded.push_str(&value),
+            (Lookup::Found(value), Some(_)) if !value.is_empty() => expanded.push_str(&value),
+            (Lookup::Missing, None) => return Err(format!("`{name}` is not set")),
+            (_, Some(fallback)) => match fallback.split_at(2) {
+                (":-", default) => expanded.push_str(default),
+                (_, "") => return Err(format!("`{name}` is not set")),
+                (_, message) => return Err(format!("`{name}` is not set: {message}")),
+            },
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Looks up `${NAME}` placeholders in the process environment, other names are skipped.
+pub(crate) fn environment_variable(_key: &str, name: &str) -> Lookup {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Lookup::Skip;
+    }
+    match std::env::var(name) {
+        Ok(value) => Lookup::Found(value),
+        Err(_) => Lookup::Missing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_placeholders() {
+        let mut lookup = |name: &str| match name {
+            "HOST" => Lookup::Found(String::from("db")),
+            "EMPTY" => Lookup::Found(String::new()),
+            "server.port" => Lookup::Skip,
+            _ => Lookup::Missing,
+        };
+        let mut expand = |text| expand(text, false, &mut lookup);
+
+        assert_eq!(
+            expand("postgres://${HOST}:${PORT:-5432}/app").unwrap(),
+            "postgres://db:5432/app"
+        );
+        assert_eq!(expand("${EMPTY:-fallback}").unwrap(), "fallback");
+        assert_eq!(expand("${EMPTY}").unwrap(), "");
+        assert_eq!(
+            expand("$${HOST} ${server.port}").unwrap(),
+            "${HOST} ${server.port}"
+        );
+        assert_eq!(expand("${PORT}").unwrap_err(), "`PORT` is not set");
+        assert_eq!(
+            expand("${PORT:?the database port}").unwrap_err(),
+            "`PORT` is not set: the database port"
+        );
+        assert_eq!(
+            expand("${PORT:?set it :- please}").unwrap_err(),
+            "`PORT` is not set: set it :- please"
+        );
+        assert_eq!(expand("${PORT:-a:?b}").unwrap(), "a:?b");
+        assert_eq!(
+            expand("${HOST").unwrap_err(),
+            "unterminated placeholder `${HOST`"
+        );
+        assert_eq!(
+            super::expand("$${HOST} ${HOST}", true, &mut lookup).unwrap(),
+            "$${HOST} db"
+        );
+    }
+}
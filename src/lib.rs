@@ -1,10 +1,118 @@
+#[cfg(feature = "tokio")]
+mod asynchronous;
+mod de;
+#[cfg(feature = "age")]
+mod decrypt;
+mod diagnostic;
+mod diff;
+mod directory;
+#[cfg(feature = "dotenv")]
+mod dotenv;
+mod environment;
+mod file;
+mod interpolate;
+mod merge;
+mod metadata;
+mod provenance;
+mod reference;
+#[cfg(feature = "reload")]
+mod reload;
+mod secret;
+#[cfg(all(unix, feature = "signal"))]
+mod signal;
+mod source;
+mod variables;
+#[cfg(feature = "watch")]
+mod watch;
+
+pub use diagnostic::FieldError;
+pub use diff::{Change, Diff};
+pub use environment::{Detection, Environment, EnvironmentDetector};
+pub use metadata::{Layer, Loaded, Priority};
+pub use provenance::{Location, Origin, Provenance, Trace};
+#[cfg(feature = "reload")]
+pub use reload::{Reload, Reloadable};
+pub use secret::Secret;
+#[cfg(all(unix, feature = "signal"))]
+pub use signal::ReloadSignal;
+pub use source::Source;
+#[cfg(feature = "tokio")]
+pub use source::{AsyncSource, BoxFuture};
+
+/// A config value, as produced by a [`Source`].
+pub use config::Value;
+
+use merge::Collected;
+#[cfg(feature = "tokio")]
+use source::AsyncSources;
+use source::Sources;
+
 use config::ConfigError;
-use strum::{Display, EnumString};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
 
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
-    #[error("Failed to parse development environment from value `{0}`")]
-    EnvironmentVariableParsing(String, #[source] strum::ParseError),
+    #[error(
+        "Failed to parse development environment from value `{0}`, expected one of: {}",
+        .1.join(", ")
+    )]
+    EnvironmentVariableParsing(String, Vec<String>),
+    #[error(
+        "No environment detected in strict mode, tried: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+    )]
+    EnvironmentMissing(Vec<EnvironmentDetector>),
+    #[error("Failed to read environment from `{0}`")]
+    EnvironmentFileAccess(PathBuf, #[source] std::io::Error),
+    #[error("Config file `{}.*` not found", .0.display())]
+    ConfigFileMissing(PathBuf),
+    #[error(
+        "Ambiguous config files, set a format precedence or remove all but one of: {}",
+        .0.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ")
+    )]
+    AmbiguousConfigFile(Vec<PathBuf>),
+    #[error("Failed to read config values from `{}`", .0.display())]
+    DirectoryAccess(PathBuf, #[source] std::io::Error),
+    #[cfg(feature = "age")]
+    #[error("Failed to load age decryption key")]
+    DecryptionKey(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[cfg(feature = "age")]
+    #[error("Failed to decrypt value at `{0}`")]
+    Decryption(String, #[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error(
+        "Failed to interpolate `{0}`{}: {2}",
+        .1.as_ref().map(|origin| format!(" at {origin}")).unwrap_or_default()
+    )]
+    Interpolation(String, Option<Origin>, String),
+    #[error("Cyclic reference: {}", .0.join(" -> "))]
+    ReferenceCycle(Vec<String>),
+    #[error(
+        "Unknown key `{}` referenced by {}",
+        .0.last().map(String::as_str).unwrap_or_default(),
+        .0[..(.0.len().saturating_sub(1))].join(" -> ")
+    )]
+    UnknownReference(Vec<String>),
+    #[error("Failed to load config from source `{0}`")]
+    Source(String, #[source] Box<dyn std::error::Error + Send + Sync>),
+    #[cfg(feature = "tokio")]
+    #[error("Source `{0}` timed out after {1:?}")]
+    SourceTimeout(String, std::time::Duration),
+    #[cfg(feature = "tokio")]
+    #[error(
+        "Source `{0}` is async and only loaded by the async builder methods, e.g. `build_async`"
+    )]
+    AsyncSourceInSyncBuild(String),
+    #[error("Failed to read `{}` referenced by environment variable `{0}`", .1.display())]
+    EnvironmentVariableFileAccess(String, PathBuf, #[source] std::io::Error),
+    #[cfg(feature = "dotenv")]
+    #[error("Failed to read environment variables from `{}`", .0.display())]
+    DotenvParsing(PathBuf, #[source] dotenvy::Error),
     #[error("Failed to get access to working directory")]
     WorkingDirectoryAccess(#[source] std::io::Error),
     #[error("Failed to set config's specifications")]
@@ -12,68 +120,428 @@ pub enum Error {
     #[error("Failed to compose config schema from sources")]
     ComposeSchema(#[source] ConfigError),
     #[error("Failed to deserialize config from schema")]
-    Deserialization(#[source] ConfigError),
+    Deserialization(#[source] Box<FieldError>),
+    #[error(
+        "Failed to deserialize config from schema, found {} error{}:\n\n{}",
+        .0.len(),
+        if .0.len() == 1 { "" } else { "s" },
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n\n")
+    )]
+    Multiple(Vec<FieldError>),
+    #[cfg(feature = "watch")]
+    #[error("Failed to watch config directory")]
+    Watch(#[source] notify_debouncer_mini::notify::Error),
+    #[cfg(all(unix, feature = "signal"))]
+    #[error("Failed to listen for reload signals")]
+    Signal(#[source] std::io::Error),
 }
 
 #[derive(derive_builder::Builder, Debug, Clone)]
-#[builder(build_fn(private, name = "prepare"))]
+#[builder(build_fn(private, name = "prepare", validate = "Self::validate"))]
 pub struct Config {
     #[builder(default = r#"String::from("APP_ENV")"#, setter(into))]
     pub environment_variable_name: String,
+    /// Environment names accepted from `environment_variable_name`, [`Environment`]'s variants
+    /// by default.
+    #[builder(default = "environment::default_environments()", setter(custom))]
+    pub environments: Vec<String>,
+    /// Environment used when `environment_variable_name` is not set. Must be one of
+    /// `environments`.
+    #[builder(default = "Environment::default().to_string()", setter(into))]
+    pub default_environment: String,
+    /// Alternative spellings mapped onto `environments`, e.g. `prod` → `production`.
+    #[builder(default, setter(custom))]
+    pub environment_aliases: HashMap<String, String>,
+    /// Match environment names and aliases ignoring ASCII case.
+    #[builder(default)]
+    pub environment_case_insensitive: bool,
+    /// Ordered environment detection chain, the first detector that finds a value wins. When
+    /// empty, only `environment_variable_name` is consulted.
+    #[builder(default, setter(custom))]
+    pub environment_detectors: Vec<EnvironmentDetector>,
+    /// Fail with [`Error::EnvironmentMissing`] instead of falling back to `default_environment`.
+    #[builder(default)]
+    pub strict_environment: bool,
+    /// Enable `strict_environment` in release builds (`cfg(not(debug_assertions))`).
+    #[builder(default)]
+    pub strict_environment_in_release: bool,
     #[builder(default = r#"String::from("config")"#, setter(into))]
     pub config_directory: String,
+    /// File stem of the layer shared by every environment, e.g. `config/default.toml`.
+    #[builder(default = r#"String::from("default")"#, setter(into))]
+    pub base_layer_name: String,
+    /// Suffix of the optional per-environment override layer, e.g.
+    /// `config/production.local.toml`.
+    #[builder(default = r#"String::from("local")"#, setter(into))]
+    pub local_layer_suffix: String,
+    /// Expand `${VAR}`, `${VAR:-default}` and `${VAR:?message}` in config file values from the
+    /// process environment. `$${` is a literal `${`.
+    #[builder(default)]
+    pub interpolate_environment_variables: bool,
+    /// Expand `${key.path}` references to other keys, e.g. `${server.port}`, once every layer
+    /// is merged. Only names containing a `.` are references.
+    #[builder(default)]
+    pub resolve_references: bool,
+    /// File extensions in order of preference, used when a layer exists in several formats,
+    /// e.g. `config/production.toml` and `config/production.yaml`. Without a preference such
+    /// layers fail with [`Error::AmbiguousConfigFile`].
+    #[builder(default, setter(custom))]
+    pub format_precedence: Vec<String>,
     #[builder(default = r#"String::from("APP")"#, setter(into))]
     pub environment_variables_source_prefix: String,
     #[builder(default = r#"String::from("_")"#, setter(into))]
     pub environment_variables_source_prefix_separator: String,
     #[builder(default = r#"String::from("__")"#, setter(into))]
     pub environment_variables_source_separator: String,
+    /// Directory of `.env` and `.env.<environment>` files, relative to the working directory.
+    /// Their variables join the environment variable layer under the same prefix and separator
+    /// rules, without being set in the process environment, which takes precedence.
+    #[cfg(feature = "dotenv")]
+    #[builder(default, setter(into, strip_option))]
+    pub dotenv_directory: Option<String>,
+    /// Suffix of environment variables that name a file holding the value, e.g. `_FILE` reads
+    /// `database.password` from the file at `APP_DATABASE__PASSWORD_FILE`. Disabled by default.
+    #[builder(default, setter(into, strip_option))]
+    pub environment_variables_file_suffix: Option<String>,
+    /// Directory with one file per value, relative to the working directory, e.g. Docker or
+    /// Kubernetes secrets mounted at `/run/secrets`. File names are key paths split by
+    /// `secrets_separator`, e.g. `database__password`. Skipped when the directory is missing.
+    #[builder(default, setter(into, strip_option))]
+    pub secrets_directory: Option<String>,
+    #[builder(default = r#"String::from("__")"#, setter(into))]
+    pub secrets_separator: String,
+    /// Where `secrets_directory` is merged, above the config files by default.
+    #[builder(default)]
+    pub secrets_priority: Priority,
+    /// Read systemd credentials (`LoadCredential=`) from `$CREDENTIALS_DIRECTORY`, if set.
+    /// Credential names are key paths split by `.` or `systemd_credentials_separator`.
+    #[builder(default)]
+    pub systemd_credentials: bool,
+    /// Only credentials starting with this prefix are read, e.g. `app.` for
+    /// `app.database.password`.
+    #[builder(default, setter(into))]
+    pub systemd_credentials_prefix: String,
+    #[builder(default = r#"String::from("__")"#, setter(into))]
+    pub systemd_credentials_separator: String,
+    /// Where the credentials are merged, above the config files by default.
+    #[builder(default)]
+    pub systemd_credentials_priority: Priority,
+    /// File with age identities, relative to the working directory, used to decrypt
+    /// `ENC[age,<base64 ciphertext>]` values.
+    #[cfg(feature = "age")]
+    #[builder(default, setter(into, strip_option))]
+    pub age_key_file: Option<String>,
+    /// Environment variable holding an age identity, e.g. `AGE-SECRET-KEY-1...`, used
+    /// alongside `age_key_file`.
+    #[cfg(feature = "age")]
+    #[builder(default, setter(into, strip_option))]
+    pub age_key_variable: Option<String>,
+    /// Custom sources added with [`add_source`](ConfigBuilder::add_source).
+    #[builder(default, setter(custom))]
+    sources: Sources,
+    /// Async sources added with [`add_async_source`](ConfigBuilder::add_async_source).
+    #[cfg(feature = "tokio")]
+    #[builder(default, setter(custom))]
+    async_sources: AsyncSources,
+    /// How long an async source may take unless it sets its own
+    /// [`timeout`](AsyncSource::timeout).
+    #[cfg(feature = "tokio")]
+    #[builder(default = "std::time::Duration::from_secs(30)")]
+    pub async_source_timeout: std::time::Duration,
+    /// Go on without [optional](AsyncSource::optional) async sources that fail or time out,
+    /// instead of failing the build.
+    #[cfg(feature = "tokio")]
+    #[builder(default)]
+    pub skip_failed_optional_sources: bool,
+    /// Report every missing or invalid value at once as [`Error::Multiple`] instead of failing
+    /// on the first one.
+    ///
+    /// Invalid values are replaced by placeholders such as `0`, `""` or `0.0.0.0` to carry on.
+    /// A value whose type rejects every placeholder, e.g. through a `try_from` conversion, is
+    /// still reported, but missing fields declared after it, and those of the structs that
+    /// contain it, may only be reported once it is fixed.
+    #[builder(default)]
+    pub collect_errors: bool,
+    /// How long [`watch`](ConfigBuilder::watch) waits for file changes to settle before
+    /// reloading.
+    #[cfg(feature = "watch")]
+    #[builder(default = "std::time::Duration::from_millis(500)")]
+    pub watch_debounce: std::time::Duration,
 }
 
 impl ConfigBuilder {
+    pub fn environments<I, S>(&mut self, environments: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.environments = Some(environments.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn format_precedence<I, S>(&mut self, extensions: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.format_precedence = Some(extensions.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn environment_alias(
+        &mut self,
+        alias: impl Into<String>,
+        environment: impl Into<String>,
+    ) -> &mut Self {
+        self.environment_aliases
+            .get_or_insert_with(HashMap::new)
+            .insert(alias.into(), environment.into());
+        self
+    }
+
+    pub fn environment_detector(&mut self, detector: EnvironmentDetector) -> &mut Self {
+        self.environment_detectors
+            .get_or_insert_with(Vec::new)
+            .push(detector);
+        self
+    }
+
+    /// Merges `source` at `priority`, after the sources already added at the same priority.
+    pub fn add_source(&mut self, priority: Priority, source: impl Source + 'static) -> &mut Self {
+        self.sources
+            .get_or_insert_with(Sources::default)
+            .0
+            .push((priority, Arc::new(source)));
+        self
+    }
+
+    /// Merges the async `source` at `priority`, after the sources already added at the same
+    /// priority. Only loaded by [`build_async`](Self::build_async).
+    #[cfg(feature = "tokio")]
+    pub fn add_async_source(
+        &mut self,
+        priority: Priority,
+        source: impl AsyncSource + 'static,
+    ) -> &mut Self {
+        self.async_sources
+            .get_or_insert_with(AsyncSources::default)
+            .0
+            .push((priority, Arc::new(source)));
+        self
+    }
+
+    /// Detects the environment without loading any config, e.g. to log which detector chose it.
+    pub fn detect_environment(&self) -> Result<Detection, Error> {
+        self.prepare()
+            .map_err(Error::Preparation)?
+            .detect_environment()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let environments = self
+            .environments
+            .clone()
+            .unwrap_or_else(environment::default_environments);
+        let default_environment = self
+            .default_environment
+            .clone()
+            .unwrap_or_else(|| Environment::default().to_string());
+
+        if !environments.contains(&default_environment) {
+            return Err(format!(
+                "Default environment `{default_environment}` is not one of: {}",
+                environments.join(", ")
+            ));
+        }
+
+        for (alias, environment) in self.environment_aliases.iter().flatten() {
+            if !environments.contains(environment) {
+                return Err(format!(
+                    "Alias `{alias}` points to unknown environment `{environment}`"
+                ));
+            }
+        }
+
+        for extension in self.format_precedence.iter().flatten() {
+            if !file::is_supported(extension) {
+                return Err(format!(
+                    "Config file extension `{extension}` is not supported, is its cargo feature enabled?"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn build<Cfg: serde::de::DeserializeOwned>(&self) -> Result<Cfg, Error> {
+        self.build_with_metadata().map(Loaded::into_inner)
+    }
+
+    /// Like [`build`](Self::build), but also returns the resolved environment and the layers
+    /// that were merged.
+    pub fn build_with_metadata<Cfg: serde::de::DeserializeOwned>(
+        &self,
+    ) -> Result<Loaded<Cfg>, Error> {
+        let config = self.prepare().map_err(Error::Preparation)?;
+        #[cfg(feature = "tokio")]
+        if let Some((_, source)) = config.async_sources.0.first() {
+            return Err(Error::AsyncSourceInSyncBuild(source.name().to_owned()));
+        }
+        config.load(Vec::new())
+    }
+}
+
+impl Config {
+    /// Loads every layer, merging `async_layers` after the custom sources of the same priority.
+    pub(crate) fn load<Cfg: serde::de::DeserializeOwned>(
+        self,
+        async_layers: Vec<(Priority, Collected)>,
+    ) -> Result<Loaded<Cfg>, Error> {
+        let detection = self.detect_environment()?;
+        let environment = &detection.environment;
+        let working_directory = std::env::current_dir().map_err(Error::WorkingDirectoryAccess)?;
+        #[cfg(feature = "dotenv")]
+        let dotenv = match &self.dotenv_directory {
+            Some(directory) => dotenv::read(&working_directory.join(directory), environment)?,
+            None => Vec::new(),
+        };
+        #[cfg(not(feature = "dotenv"))]
+        let dotenv = Vec::new();
+        #[cfg(feature = "age")]
+        let mut keys = decrypt::Keys::new(
+            self.age_key_file
+                .as_ref()
+                .map(|file| working_directory.join(file)),
+            self.age_key_variable.clone(),
+        );
         let Config {
-            environment_variable_name,
             config_directory,
+            base_layer_name,
+            local_layer_suffix,
+            format_precedence,
+            interpolate_environment_variables,
+            resolve_references,
             environment_variables_source_prefix,
             environment_variables_source_prefix_separator,
             environment_variables_source_separator,
-        } = self.prepare().map_err(Error::Preparation)?;
+            environment_variables_file_suffix,
+            secrets_directory,
+            secrets_separator,
+            secrets_priority,
+            systemd_credentials,
+            systemd_credentials_prefix,
+            systemd_credentials_separator,
+            systemd_credentials_priority,
+            sources,
+            collect_errors,
+            ..
+        } = self;
 
-        let environment = match std::env::var(environment_variable_name) {
-            Ok(env) => env
-                .parse()
-                .map_err(|e| Error::EnvironmentVariableParsing(env, e))?,
-            Err(_) => Environment::default(),
-        };
+        let config_directory = working_directory.join(config_directory);
+
+        let mut prioritized = Vec::new();
+        if let Some(directory) = secrets_directory {
+            let root = working_directory.join(directory);
+            if let Some(secrets) = directory::collect(&root, "", &secrets_separator)? {
+                prioritized.push((secrets_priority, secrets));
+            }
+        }
+        if let Some(root) =
+            std::env::var_os("CREDENTIALS_DIRECTORY").filter(|_| systemd_credentials)
+        {
+            if let Some(credentials) = directory::collect(
+                Path::new(&root),
+                &systemd_credentials_prefix,
+                &systemd_credentials_separator,
+            )? {
+                prioritized.push((systemd_credentials_priority, credentials));
+            }
+        }
+        for (priority, source) in sources.0 {
+            prioritized.push((priority, source::collect(source.as_ref())?));
+        }
+        prioritized.extend(async_layers);
 
-        let working_directory = std::env::current_dir()
-            .map_err(Error::WorkingDirectoryAccess)?
-            .join(config_directory)
-            .join(environment.to_string());
+        let file_layers = [
+            (base_layer_name, false),
+            (environment.clone(), true),
+            (format!("{environment}.{local_layer_suffix}"), false),
+        ];
 
-        let file_source = config::File::from(working_directory);
-        let env_vars_source =
-            config::Environment::with_prefix(&environment_variables_source_prefix)
-                .prefix_separator(&environment_variables_source_prefix_separator)
-                .separator(&environment_variables_source_separator);
+        let mut collected = merge::take(&mut prioritized, Priority::BeforeFiles);
+        for (name, required) in file_layers {
+            let stem = config_directory.join(name);
+            match file::find(&stem, &format_precedence)? {
+                Some((path, format)) => collected.push(file::load(
+                    &path,
+                    format,
+                    interpolate_environment_variables,
+                    resolve_references,
+                )?),
+                None if required => return Err(Error::ConfigFileMissing(stem)),
+                None => {}
+            }
+        }
+        collected.extend(merge::take(&mut prioritized, Priority::AfterFiles));
+        collected.extend(variables::collect(
+            &environment_variables_source_prefix,
+            &environment_variables_source_prefix_separator,
+            &environment_variables_source_separator,
+            environment_variables_file_suffix.as_deref(),
+            dotenv,
+        )?);
+        collected.extend(merge::take(
+            &mut prioritized,
+            Priority::AfterEnvironmentVariables,
+        ));
 
-        config::Config::builder()
-            .add_source(file_source)
-            .add_source(env_vars_source)
-            .build()
-            .map_err(Error::ComposeSchema)?
-            .try_deserialize()
-            .map_err(Error::Deserialization)
-    }
-}
+        let mut tree = config::Map::new();
+        let mut layers = Vec::new();
+        let mut provenance = Provenance::default();
+        for Collected {
+            layer,
+            values,
+            origins,
+        } in collected
+        {
+            for (key, origin) in origins {
+                provenance.record(key, origin);
+            }
+            merge::merge(&mut tree, values);
+            layers.push(layer);
+        }
+        #[cfg(feature = "age")]
+        let decrypted = decrypt::decrypt(&mut tree, &mut keys)?;
+        if resolve_references {
+            reference::resolve(&mut tree, &provenance)?;
+        }
+
+        let (config, secrets) =
+            de::deserialize(tree.clone(), collect_errors).map_err(|errors| {
+                let mut errors: Vec<FieldError> = errors
+                    .into_iter()
+                    .map(|e| FieldError::new(e, &provenance))
+                    .collect();
+                match errors.pop() {
+                    Some(error) if !collect_errors => Error::Deserialization(Box::new(error)),
+                    error => Error::Multiple(errors.into_iter().chain(error).collect()),
+                }
+            })?;
+        // The merged values are only kept for diffing, without secrets in plaintext.
+        #[cfg(feature = "age")]
+        let secrets = secrets.into_iter().chain(decrypted).collect();
+        secret::fingerprint(&mut tree, &secrets);
 
-#[derive(EnumString, Display, Default)]
-#[strum(serialize_all = "snake_case")]
-pub enum Environment {
-    #[default]
-    Local,
-    Production,
+        Ok(Loaded {
+            config,
+            environment: detection,
+            config_directory,
+            layers,
+            provenance,
+            loaded_at: SystemTime::now(),
+            values: tree,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -81,6 +549,10 @@ mod tests {
     use super::*;
     use serde::Deserialize;
 
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
     #[test]
     fn builder() {
         #[derive(Deserialize)]
@@ -94,7 +566,842 @@ mod tests {
         }
         std::env::set_var("APP_ENV", "production");
         std::env::set_var("APP_BAR__BAZ", "777");
-        let ret: FooConfig = ConfigBuilder::default().build().unwrap();
+        let ret: FooConfig = ConfigBuilder::default()
+            .config_directory(fixture("layered"))
+            .build()
+            .unwrap();
         assert_eq!(ret.bar.baz, 777);
     }
+
+    #[test]
+    fn layered_files() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct BarConfig {
+            baz: u16,
+            qux: String,
+            quux: String,
+        }
+        std::env::set_var("LAYERED_ENV", "production");
+        let ret: FooConfig = ConfigBuilder::default()
+            .environment_variable_name("LAYERED_ENV")
+            .environment_variables_source_prefix("LAYERED")
+            .config_directory(fixture("layered"))
+            .build()
+            .unwrap();
+        assert_eq!(ret.bar.baz, 3);
+        assert_eq!(ret.bar.qux, "production");
+        assert_eq!(ret.bar.quux, "default");
+    }
+
+    #[test]
+    fn custom_environments() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct BarConfig {
+            baz: u16,
+        }
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("CUSTOM_ENV")
+            .environment_variables_source_prefix("CUSTOM")
+            .config_directory(fixture("layered"))
+            .environments(["local", "staging", "production"]);
+
+        std::env::set_var("CUSTOM_ENV", "staging");
+        let ret: FooConfig = builder.build().unwrap();
+        assert_eq!(ret.bar.baz, 4);
+
+        std::env::set_var("CUSTOM_ENV", "qa");
+        let err = builder.build::<FooConfig>().err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse development environment from value `qa`, expected one of: local, \
+             staging, production"
+        );
+    }
+
+    #[test]
+    fn environment_aliases() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct BarConfig {
+            qux: String,
+        }
+        std::env::set_var("ALIASED_ENV", "PRD");
+        let ret: FooConfig = ConfigBuilder::default()
+            .environment_variable_name("ALIASED_ENV")
+            .environment_variables_source_prefix("ALIASED")
+            .config_directory(fixture("layered"))
+            .environment_alias("prod", "production")
+            .environment_alias("prd", "production")
+            .environment_case_insensitive(true)
+            .build()
+            .unwrap();
+        assert_eq!(ret.bar.qux, "production");
+
+        let err = ConfigBuilder::default()
+            .environment_alias("stg", "staging")
+            .build::<FooConfig>()
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::Preparation(_)));
+    }
+
+    #[test]
+    fn strict_environment() {
+        #[derive(Deserialize)]
+        struct FooConfig {}
+
+        let err = ConfigBuilder::default()
+            .environment_variable_name("STRICT_ENV")
+            .config_directory(fixture("layered"))
+            .strict_environment(true)
+            .build::<FooConfig>()
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            Error::EnvironmentMissing(detectors)
+                if detectors == [EnvironmentDetector::Variable(String::from("STRICT_ENV"))]
+        ));
+    }
+
+    #[test]
+    fn build_with_metadata() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct BarConfig {
+            baz: u16,
+        }
+        std::env::set_var("META_ENV", "production");
+        std::env::set_var("META_BAR__BAZ", "42");
+        let before = SystemTime::now();
+        let loaded: Loaded<FooConfig> = ConfigBuilder::default()
+            .environment_variable_name("META_ENV")
+            .environment_variables_source_prefix("META")
+            .config_directory(fixture("layered"))
+            .build_with_metadata()
+            .unwrap();
+        assert_eq!(loaded.bar.baz, 42);
+        assert_eq!(loaded.environment.environment, "production");
+        assert_eq!(
+            loaded.environment.detector,
+            Some(EnvironmentDetector::Variable(String::from("META_ENV")))
+        );
+        assert_eq!(loaded.config_directory, PathBuf::from(fixture("layered")));
+        assert_eq!(
+            loaded.layers,
+            [
+                Layer::File(loaded.config_directory.join("default.toml")),
+                Layer::File(loaded.config_directory.join("production.toml")),
+                Layer::File(loaded.config_directory.join("production.local.toml")),
+                Layer::EnvironmentVariables(vec![
+                    String::from("META_BAR__BAZ"),
+                    String::from("META_ENV"),
+                ]),
+            ]
+        );
+        assert!(loaded.loaded_at >= before);
+    }
+
+    #[test]
+    fn provenance() {
+        #[derive(Deserialize)]
+        struct FooConfig {}
+
+        std::env::set_var("TRACED_ENV", "production");
+        std::env::set_var("TRACED_BAR__BAZ", "42");
+        let loaded: Loaded<FooConfig> = ConfigBuilder::default()
+            .environment_variable_name("TRACED_ENV")
+            .environment_variables_source_prefix("TRACED")
+            .config_directory(fixture("layered"))
+            .build_with_metadata()
+            .unwrap();
+        let file = |name: &str, line| Origin::File {
+            path: loaded.config_directory.join(name),
+            location: Some(Location { line, column: 1 }),
+        };
+
+        assert_eq!(
+            loaded.provenance.get("bar.baz"),
+            Some(&Trace {
+                origin: Origin::EnvironmentVariable(String::from("TRACED_BAR__BAZ")),
+                overridden: vec![
+                    file("default.toml", 2),
+                    file("production.toml", 2),
+                    file("production.local.toml", 2),
+                ],
+            })
+        );
+        assert_eq!(
+            loaded.provenance.get("bar.quux"),
+            Some(&Trace {
+                origin: file("default.toml", 4),
+                overridden: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn deserialization_error() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct BarConfig {
+            baz: u8,
+            qux: u16,
+            missing: String,
+        }
+        std::env::set_var("INVALID_ENV", "production");
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("INVALID_ENV")
+            .environment_variables_source_prefix("INVALID")
+            .config_directory(fixture("invalid"));
+
+        let Err(Error::Deserialization(err)) = builder.build::<FooConfig>() else {
+            panic!("expected a deserialization error");
+        };
+        let path = PathBuf::from(fixture("invalid")).join("production.toml");
+        assert_eq!(err.key, "bar.baz");
+        assert_eq!(err.expected.as_deref(), Some("u8"));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "invalid value: integer `300`, expected u8 at `bar.baz`\n \
+                 --> {}:2:3\n  |\n2 |   baz = 300\n  |   ^^^^^^^^^",
+                path.display()
+            )
+        );
+
+        std::env::set_var("INVALID_BAR__BAZ", "3");
+        std::env::set_var("INVALID_BAR__QUX", "abc");
+        let Err(Error::Deserialization(err)) = builder.build::<FooConfig>() else {
+            panic!("expected a deserialization error");
+        };
+        assert_eq!(
+            err.to_string(),
+            "invalid type: string \"abc\", expected u16 at `bar.qux`\n --> env INVALID_BAR__QUX"
+        );
+
+        std::env::set_var("INVALID_BAR__QUX", "3");
+        let Err(Error::Deserialization(err)) = builder.build::<FooConfig>() else {
+            panic!("expected a deserialization error");
+        };
+        assert_eq!(err.to_string(), "missing field `missing` at `bar.missing`");
+        assert_eq!(err.origin, None);
+    }
+
+    #[test]
+    fn collect_errors() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct FooConfig {
+            bar: BarConfig,
+            missing: BarConfig,
+            #[serde(default)]
+            defaulted: u16,
+            optional: Option<u16>,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct BarConfig {
+            baz: u8,
+            qux: u16,
+            list: Vec<u16>,
+            missing: String,
+            level: Level,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        enum Level {
+            Low,
+            High,
+        }
+        std::env::set_var("COLLECTED_ENV", "production");
+        std::env::set_var("COLLECTED_BAR__QUX", "abc");
+        std::env::set_var("COLLECTED_BAR__LEVEL", "Medium");
+        let Err(Error::Multiple(errors)) = ConfigBuilder::default()
+            .environment_variable_name("COLLECTED_ENV")
+            .environment_variables_source_prefix("COLLECTED")
+            .config_directory(fixture("invalid"))
+            .collect_errors(true)
+            .build::<FooConfig>()
+        else {
+            panic!("expected multiple errors");
+        };
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|e| (e.key.as_str(), e.message.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ("bar.baz", "invalid value: integer `300`, expected u8"),
+                (
+                    "bar.level",
+                    "unknown variant `Medium`, expected `Low` or `High`"
+                ),
+                ("bar.list[1]", "invalid type: string \"x\", expected u16"),
+                ("bar.missing", "missing field `missing`"),
+                ("bar.qux", "invalid type: string \"abc\", expected u16"),
+                ("missing", "missing field `missing`"),
+            ]
+        );
+    }
+
+    #[test]
+    fn collect_errors_past_validated_fields() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct FooConfig {
+            a: Listen,
+            b: Listen,
+            c: u16,
+            d: Unprivileged,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Listen {
+            #[serde(default = "localhost")]
+            addr: std::net::IpAddr,
+            port: u16,
+        }
+
+        fn localhost() -> std::net::IpAddr {
+            std::net::Ipv4Addr::LOCALHOST.into()
+        }
+
+        /// Rejects every placeholder value.
+        #[derive(Debug, Deserialize)]
+        #[serde(try_from = "u16")]
+        struct Unprivileged(#[allow(dead_code)] u16);
+
+        impl TryFrom<u16> for Unprivileged {
+            type Error = String;
+
+            fn try_from(port: u16) -> Result<Self, Self::Error> {
+                match port {
+                    1024.. => Ok(Self(port)),
+                    _ => Err(format!("port {port} is privileged")),
+                }
+            }
+        }
+
+        std::env::set_var("VALIDATED_ENV", "production");
+        std::env::set_var("VALIDATED_A__ADDR", "not-an-ip");
+        std::env::set_var("VALIDATED_A__PORT", "x");
+        std::env::set_var("VALIDATED_B__PORT", "y");
+        std::env::set_var("VALIDATED_D", "80");
+        let Err(error @ Error::Multiple(_)) = ConfigBuilder::default()
+            .environment_variable_name("VALIDATED_ENV")
+            .environment_variables_source_prefix("VALIDATED")
+            .config_directory(fixture("layered"))
+            .collect_errors(true)
+            .build::<FooConfig>()
+        else {
+            panic!("expected multiple errors");
+        };
+        assert!(error.to_string().contains("found 5 errors"), "{error}");
+        let Error::Multiple(errors) = error else {
+            unreachable!()
+        };
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|e| (e.key.as_str(), e.message.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ("a.addr", "invalid IP address syntax"),
+                ("a.port", "invalid type: string \"x\", expected u16"),
+                ("b.port", "invalid type: string \"y\", expected u16"),
+                ("c", "missing field `c`"),
+                ("d", "port 80 is privileged"),
+            ]
+        );
+    }
+
+    #[test]
+    fn environment_detectors() {
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_detector(EnvironmentDetector::Variable(String::from("DETECTED_ENV")))
+            .environment_detector(EnvironmentDetector::Argument(String::from("--env")))
+            .environment_detector(EnvironmentDetector::File(
+                fixture("environment-marker").into(),
+            ))
+            .environment_alias("prod", "production");
+
+        let detection = builder.detect_environment().unwrap();
+        assert_eq!(detection.environment, "production");
+        assert_eq!(
+            detection.detector,
+            Some(EnvironmentDetector::File(
+                fixture("environment-marker").into()
+            ))
+        );
+
+        std::env::set_var("DETECTED_ENV", "local");
+        let detection = builder.detect_environment().unwrap();
+        assert_eq!(detection.environment, "local");
+        assert_eq!(
+            detection.detector,
+            Some(EnvironmentDetector::Variable(String::from("DETECTED_ENV")))
+        );
+
+        let detection = ConfigBuilder::default()
+            .environment_detector(EnvironmentDetector::Variable(String::from(
+                "UNDETECTED_ENV",
+            )))
+            .detect_environment()
+            .unwrap();
+        assert_eq!(detection.environment, "local");
+        assert_eq!(detection.detector, None);
+    }
+
+    #[cfg(all(feature = "yaml", feature = "json"))]
+    #[test]
+    fn file_formats() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct BarConfig {
+            baz: u16,
+            qux: String,
+        }
+        std::env::set_var("FORMATS_ENV", "production");
+        let ret: FooConfig = ConfigBuilder::default()
+            .environment_variable_name("FORMATS_ENV")
+            .environment_variables_source_prefix("FORMATS")
+            .config_directory(fixture("formats"))
+            .build()
+            .unwrap();
+        assert_eq!(ret.bar.baz, 1);
+        assert_eq!(ret.bar.qux, "production");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn ambiguous_files() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct BarConfig {
+            baz: u16,
+        }
+        std::env::set_var("AMBIGUOUS_ENV", "production");
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("AMBIGUOUS_ENV")
+            .environment_variables_source_prefix("AMBIGUOUS")
+            .config_directory(fixture("ambiguous"));
+
+        let err = builder.build::<FooConfig>().err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Ambiguous config files, set a format precedence or remove all but one of: \
+                 {0}/production.toml, {0}/production.json",
+                fixture("ambiguous")
+            )
+        );
+
+        let ret: FooConfig = builder.format_precedence(["json", "toml"]).build().unwrap();
+        assert_eq!(ret.bar.baz, 2);
+
+        let err = builder
+            .format_precedence(["xml"])
+            .build::<FooConfig>()
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::Preparation(_)));
+    }
+
+    #[cfg(feature = "dotenv")]
+    #[test]
+    fn dotenv() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct BarConfig {
+            baz: u16,
+            qux: String,
+            quux: String,
+        }
+        std::env::set_var("DOTENV_ENV", "production");
+        std::env::set_var("DOTENV_BAR__QUX", "environment");
+        let ret: Loaded<FooConfig> = ConfigBuilder::default()
+            .environment_variable_name("DOTENV_ENV")
+            .environment_variables_source_prefix("DOTENV")
+            .config_directory(fixture("layered"))
+            .dotenv_directory(fixture("dotenv"))
+            .build_with_metadata()
+            .unwrap();
+        assert_eq!(ret.bar.baz, 20);
+        assert_eq!(ret.bar.qux, "environment");
+        assert_eq!(ret.bar.quux, "default");
+        assert!(std::env::var("DOTENV_BAR__BAZ").is_err());
+
+        let dotenv = PathBuf::from(fixture("dotenv"));
+        assert_eq!(
+            ret.provenance.get("bar.baz").unwrap().origin,
+            Origin::file(&dotenv.join(".env.production"), None)
+        );
+        assert_eq!(
+            ret.layers.last(),
+            Some(&Layer::EnvironmentVariables(vec![
+                String::from("DOTENV_BAR__BAZ"),
+                String::from("DOTENV_BAR__QUX"),
+                String::from("DOTENV_ENV"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn secrets_directory() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct BarConfig {
+            qux: String,
+            quux: String,
+        }
+        std::env::set_var("SECRETS_ENV", "production");
+        std::env::set_var("SECRETS_BAR__QUX", "environment");
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("SECRETS_ENV")
+            .environment_variables_source_prefix("SECRETS")
+            .config_directory(fixture("layered"))
+            .secrets_directory(fixture("secrets"));
+
+        let ret: FooConfig = builder.build().unwrap();
+        assert_eq!(ret.bar.qux, "environment");
+        assert_eq!(ret.bar.quux, "default");
+
+        let ret: Loaded<FooConfig> = builder
+            .secrets_priority(Priority::AfterEnvironmentVariables)
+            .build_with_metadata()
+            .unwrap();
+        assert_eq!(ret.bar.qux, "secret");
+        assert_eq!(
+            ret.provenance.get("bar.qux").unwrap().origin,
+            Origin::file(&PathBuf::from(fixture("secrets")).join("bar__qux"), None)
+        );
+        assert_eq!(
+            ret.layers.last(),
+            Some(&Layer::Directory(fixture("secrets").into()))
+        );
+
+        let ret: FooConfig = builder
+            .secrets_directory(fixture("missing"))
+            .build()
+            .unwrap();
+        assert_eq!(ret.bar.qux, "environment");
+    }
+
+    #[test]
+    fn environment_variable_files() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct BarConfig {
+            qux: String,
+        }
+        std::env::set_var("INDIRECT_ENV", "production");
+        std::env::set_var(
+            "INDIRECT_BAR__QUX_FILE",
+            format!("{}/bar__qux", fixture("secrets")),
+        );
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("INDIRECT_ENV")
+            .environment_variables_source_prefix("INDIRECT")
+            .config_directory(fixture("layered"))
+            .environment_variables_file_suffix("_FILE");
+
+        let ret: FooConfig = builder.build().unwrap();
+        assert_eq!(ret.bar.qux, "secret");
+
+        // Only the suffix is left after the prefix, so this is an ordinary variable.
+        std::env::set_var("INDIRECT_FILE", "plain");
+        let loaded = builder.build_with_metadata::<FooConfig>().unwrap();
+        assert_eq!(
+            loaded.provenance.get("file").unwrap().origin,
+            Origin::EnvironmentVariable("INDIRECT_FILE".into())
+        );
+        std::env::remove_var("INDIRECT_FILE");
+
+        std::env::set_var("INDIRECT_BAR__QUX_FILE", fixture("missing"));
+        let err = builder.build::<FooConfig>().err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Failed to read `{}` referenced by environment variable `INDIRECT_BAR__QUX_FILE`",
+                fixture("missing")
+            )
+        );
+    }
+
+    #[test]
+    fn systemd_credentials() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct BarConfig {
+            qux: String,
+        }
+        std::env::set_var("SYSTEMD_ENV", "production");
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("SYSTEMD_ENV")
+            .environment_variables_source_prefix("SYSTEMD")
+            .config_directory(fixture("layered"))
+            .systemd_credentials(true)
+            .systemd_credentials_prefix("app.");
+
+        let ret: FooConfig = builder.build().unwrap();
+        assert_eq!(ret.bar.qux, "production");
+
+        std::env::set_var("CREDENTIALS_DIRECTORY", fixture("credentials"));
+        let ret = builder.build_with_metadata::<FooConfig>();
+        std::env::remove_var("CREDENTIALS_DIRECTORY");
+        let ret = ret.unwrap();
+        assert_eq!(ret.bar.qux, "credential");
+        assert_eq!(ret.provenance.get("tls.key"), None);
+        assert_eq!(ret.provenance.get("directory"), None);
+    }
+
+    #[cfg(feature = "age")]
+    #[test]
+    fn encrypted_values() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            database: DatabaseConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct DatabaseConfig {
+            user: String,
+            password: Secret<String>,
+        }
+        std::env::set_var("ENCRYPTED_ENV", "production");
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("ENCRYPTED_ENV")
+            .environment_variables_source_prefix("ENCRYPTED")
+            .config_directory(fixture("encrypted"));
+
+        let err = builder.build::<FooConfig>().err().unwrap();
+        assert!(matches!(err, Error::DecryptionKey(_)));
+
+        // Relative to the working directory, which is the crate root under `cargo test`.
+        builder.age_key_file("tests/fixtures/encrypted/key.txt");
+        let ret: FooConfig = builder.build().unwrap();
+        assert_eq!(ret.database.user, "admin");
+        assert_eq!(ret.database.password.expose(), "hunter2");
+
+        let key = std::fs::read_to_string(format!("{}/key.txt", fixture("encrypted"))).unwrap();
+        std::env::set_var("ENCRYPTED_AGE_KEY", key);
+        let ret: FooConfig = ConfigBuilder::default()
+            .environment_variable_name("ENCRYPTED_ENV")
+            .environment_variables_source_prefix("ENCRYPTED_CONFIG")
+            .config_directory(fixture("encrypted"))
+            .age_key_variable("ENCRYPTED_AGE_KEY")
+            .build()
+            .unwrap();
+        assert_eq!(ret.database.password.expose(), "hunter2");
+    }
+
+    #[test]
+    fn interpolation() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            database: DatabaseConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct DatabaseConfig {
+            url: String,
+        }
+        std::env::set_var("INTERPOLATED_ENV", "production");
+        std::env::set_var("INTERPOLATED_DB_PORT", "5432");
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("INTERPOLATED_ENV")
+            .environment_variables_source_prefix("INTERPOLATED")
+            .config_directory(fixture("interpolated"));
+
+        let ret: FooConfig = builder.build().unwrap();
+        assert_eq!(
+            ret.database.url,
+            "postgres://${INTERPOLATED_DB_HOST:-localhost}:${INTERPOLATED_DB_PORT:?set the database port}/app"
+        );
+
+        builder.interpolate_environment_variables(true);
+        let ret: FooConfig = builder.build().unwrap();
+        assert_eq!(ret.database.url, "postgres://localhost:5432/app");
+
+        std::env::remove_var("INTERPOLATED_DB_PORT");
+        let err = builder.build::<FooConfig>().err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Failed to interpolate `database.url` at {}/production.toml:2:1: \
+                 `INTERPOLATED_DB_PORT` is not set: set the database port",
+                fixture("interpolated")
+            )
+        );
+    }
+
+    #[test]
+    fn references() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            public_url: String,
+            literal: String,
+        }
+        std::env::set_var("REFERENCES_ENV", "production");
+        std::env::set_var("REFERENCES_SERVER__PORT", "8443");
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("REFERENCES_ENV")
+            .environment_variables_source_prefix("REFERENCES")
+            .config_directory(fixture("references"))
+            .resolve_references(true);
+        let ret: FooConfig = builder.build().unwrap();
+        assert_eq!(ret.public_url, "https://example.com:8443");
+        assert_eq!(ret.literal, "${server.host} ${REFERENCES_ENV}");
+
+        // Escapes are only unescaped once with both passes enabled.
+        builder.interpolate_environment_variables(true);
+        let ret: FooConfig = builder.build().unwrap();
+        assert_eq!(ret.public_url, "https://example.com:8443");
+        assert_eq!(ret.literal, "${server.host} ${REFERENCES_ENV}");
+    }
+
+    #[test]
+    fn custom_sources() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct BarConfig {
+            baz: u16,
+            qux: String,
+        }
+
+        struct Static(
+            &'static str,
+            Result<Vec<(&'static str, Value)>, &'static str>,
+        );
+
+        impl Source for Static {
+            fn name(&self) -> &str {
+                self.0
+            }
+
+            fn collect(
+                &self,
+            ) -> Result<Vec<(String, Value)>, Box<dyn std::error::Error + Send + Sync>>
+            {
+                self.1
+                    .clone()
+                    .map(|values| {
+                        values
+                            .into_iter()
+                            .map(|(key, value)| (key.to_owned(), value))
+                            .collect()
+                    })
+                    .map_err(Into::into)
+            }
+        }
+
+        std::env::set_var("SOURCES_ENV", "production");
+        std::env::set_var("SOURCES_BAR__QUX", "environment");
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("SOURCES_ENV")
+            .environment_variables_source_prefix("SOURCES")
+            .config_directory(fixture("layered"))
+            .add_source(
+                Priority::AfterFiles,
+                Static(
+                    "vault",
+                    Ok(vec![("bar.baz", 10.into()), ("bar.qux", "vault".into())]),
+                ),
+            );
+
+        let ret: Loaded<FooConfig> = builder.build_with_metadata().unwrap();
+        assert_eq!(ret.bar.baz, 10);
+        assert_eq!(ret.bar.qux, "environment");
+        assert_eq!(
+            ret.provenance.get("bar.baz").unwrap().origin,
+            Origin::Source(String::from("vault"))
+        );
+        assert!(ret.layers.contains(&Layer::Source(String::from("vault"))));
+
+        builder.add_source(
+            Priority::AfterEnvironmentVariables,
+            Static("overrides", Ok(vec![("bar.baz", "x".into())])),
+        );
+        let err = builder.build::<FooConfig>().err().unwrap();
+        assert_eq!(
+            std::error::Error::source(&err).unwrap().to_string(),
+            "invalid type: string \"x\", expected u16 at `bar.baz`\n --> source overrides"
+        );
+
+        builder.add_source(Priority::BeforeFiles, Static("broken", Err("unreachable")));
+        let err = builder.build::<FooConfig>().err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Failed to load config from source `broken`"
+        );
+    }
 }
@@ -0,0 +1,81 @@
+use crate::{Layer, Origin, Priority};
+use config::{Map, Value, ValueKind};
+
+/// Deep-merges `layer` over `tree`: tables are merged key by key, everything else is replaced.
+pub(crate) fn merge(tree: &mut Map<String, Value>, layer: Map<String, Value>) {
+    for (key, value) in layer {
+        match (tree.get_mut(&key), value) {
+            (
+                Some(Value {
+                    kind: ValueKind::Table(existing),
+                    ..
+                }),
+                Value {
+                    kind: ValueKind::Table(table),
+                    ..
+                },
+            ) => merge(existing, table),
+            (_, value) => {
+                tree.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Sets the value at a dotted key path, replacing non-table values on the way.
+pub(crate) fn insert(tree: &mut Map<String, Value>, key: &str, value: Value) {
+    match key.split_once('.') {
+        Some((head, rest)) => {
+            let entry = tree
+                .entry(head.to_owned())
+                .or_insert_with(|| Value::new(None, Map::<String, Value>::new()));
+            if !matches!(entry.kind, ValueKind::Table(_)) {
+                *entry = Value::new(None, Map::<String, Value>::new());
+            }
+            if let ValueKind::Table(table) = &mut entry.kind {
+                insert(table, rest, value);
+            }
+        }
+        None => {
+            tree.insert(key.to_owned(), value);
+        }
+    }
+}
+
+/// Dotted key paths of every leaf, i.e. every value that is not a non-empty table.
+pub(crate) fn leaves(table: &Map<String, Value>) -> Vec<String> {
+    let mut leaves = Vec::new();
+    for (key, value) in table {
+        match &value.kind {
+            ValueKind::Table(table) if !table.is_empty() => leaves.extend(
+                self::leaves(table)
+                    .into_iter()
+                    .map(|leaf| format!("{key}.{leaf}")),
+            ),
+            _ => leaves.push(key.clone()),
+        }
+    }
+    leaves.sort();
+    leaves
+}
+
+/// The values of a single layer and the origin of each of its leaves.
+pub(crate) struct Collected {
+    pub(crate) layer: Layer,
+    pub(crate) values: Map<String, Value>,
+    pub(crate) origins: Vec<(String, Origin)>,
+}
+
+/// Removes the layers merged at `priority` from `layers`, keeping their order.
+pub(crate) fn take(layers: &mut Vec<(Priority, Collected)>, priority: Priority) -> Vec<Collected> {
+    let mut taken = Vec::new();
+    let mut index = 0;
+    while index < layers.len() {
+        if layers[index].0 == priority {
+            taken.push(layers.remove(index).1);
+        } else {
+            index += 1;
+        }
+    }
+    taken
+}
@@ -0,0 +1,80 @@
+use crate::{Detection, Diff, Provenance};
+use config::{Map, Value};
+use std::{fmt, ops::Deref, path::PathBuf, time::SystemTime};
+
+/// A deserialized config together with a description of how it was loaded, see
+/// [`ConfigBuilder::build_with_metadata`](crate::ConfigBuilder::build_with_metadata).
+#[derive(Clone)]
+pub struct Loaded<Cfg> {
+    pub config: Cfg,
+    /// The resolved environment and the detector that chose it.
+    pub environment: Detection,
+    /// Absolute path of the config directory.
+    pub config_directory: PathBuf,
+    /// The layers that contributed to `config`, in merge order.
+    pub layers: Vec<Layer>,
+    /// The origin of every value in `config`.
+    pub provenance: Provenance,
+    pub loaded_at: SystemTime,
+    /// The merged values `config` was deserialized from, with the values of
+    /// [`Secret`](crate::Secret)s and decrypted values replaced by fingerprints.
+    pub(crate) values: Map<String, Value>,
+}
+
+impl<Cfg> Loaded<Cfg> {
+    pub fn into_inner(self) -> Cfg {
+        self.config
+    }
+
+    /// What changed in the merged values since `previous` was loaded.
+    pub fn diff<Previous>(&self, previous: &Loaded<Previous>) -> Diff {
+        Diff::new(&previous.values, &self.values)
+    }
+}
+
+impl<Cfg: fmt::Debug> fmt::Debug for Loaded<Cfg> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Loaded")
+            .field("config", &self.config)
+            .field("environment", &self.environment)
+            .field("config_directory", &self.config_directory)
+            .field("layers", &self.layers)
+            .field("provenance", &self.provenance)
+            .field("loaded_at", &self.loaded_at)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Cfg> Deref for Loaded<Cfg> {
+    type Target = Cfg;
+
+    fn deref(&self) -> &Self::Target {
+        &self.config
+    }
+}
+
+/// A single source merged into the config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layer {
+    /// An absolute path to a config file.
+    File(PathBuf),
+    /// Names of the environment variables matching the configured prefix.
+    EnvironmentVariables(Vec<String>),
+    /// An absolute path to a directory with one file per value.
+    Directory(PathBuf),
+    /// A custom [`Source`](crate::Source), by name.
+    Source(String),
+}
+
+/// Where an additional layer is merged relative to the config files and environment variables.
+/// Layers merged later override earlier ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Priority {
+    /// Below every config file.
+    BeforeFiles,
+    /// Above the config files and below environment variables.
+    #[default]
+    AfterFiles,
+    /// Above everything else.
+    AfterEnvironmentVariables,
+}
@@ -0,0 +1,180 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Where a single config value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// A config file. `location` is known for TOML files only.
+    File {
+        path: PathBuf,
+        location: Option<Location>,
+    },
+    /// An environment variable, by name.
+    EnvironmentVariable(String),
+    /// A custom [`Source`](crate::Source), by name.
+    Source(String),
+}
+
+/// A 1-based line and column in a config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Origin {
+    pub(crate) fn file(path: &Path, location: Option<Location>) -> Self {
+        Self::File {
+            path: path.to_path_buf(),
+            location,
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File {
+                path,
+                location: Some(Location { line, column }),
+            } => write!(f, "{}:{line}:{column}", path.display()),
+            Self::File {
+                path,
+                location: None,
+            } => write!(f, "{}", path.display()),
+            Self::EnvironmentVariable(name) => write!(f, "env {name}"),
+            Self::Source(name) => write!(f, "source {name}"),
+        }
+    }
+}
+
+/// The origin of a value and the origins it overrode, lowest layer first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub origin: Origin,
+    pub overridden: Vec<Origin>,
+}
+
+/// Origins of every value in the merged config, keyed by dotted key path such as `bar.baz`.
+///
+/// The [`Display`](fmt::Display) implementation renders it as a table.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    traces: BTreeMap<String, Trace>,
+}
+
+impl Provenance {
+    pub fn get(&self, key: &str) -> Option<&Trace> {
+        self.traces.get(key)
+    }
+
+    /// The trace of `key` or, for array elements and inline values, of its closest parent.
+    pub(crate) fn closest(&self, key: &str) -> Option<&Trace> {
+        std::iter::successors(Some(key), |key| {
+            key.rfind(['.', '[']).map(|index| &key[..index])
+        })
+        .find_map(|key| self.get(key))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Trace)> {
+        self.traces.iter().map(|(key, trace)| (key.as_str(), trace))
+    }
+
+    /// Records that `key` was set by `origin`, dropping the traces of values it replaced.
+    pub(crate) fn record(&mut self, key: String, origin: Origin) {
+        let mut overridden = Vec::new();
+        // A scalar replaces a whole table and a table replaces a scalar.
+        let descendants = format!("{key}.");
+        self.traces.retain(|other, trace| {
+            let replaced = other.starts_with(&descendants) || key.starts_with(&format!("{other}."));
+            if replaced {
+                overridden.push(trace.origin.clone());
+            }
+            !replaced
+        });
+        if let Some(previous) = self.traces.remove(&key) {
+            overridden.extend(previous.overridden);
+            overridden.push(previous.origin);
+        }
+        overridden.dedup();
+
+        self.traces.insert(key, Trace { origin, overridden });
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const HEADER: [&str; 3] = ["KEY", "SOURCE", "OVERRIDES"];
+
+        let rows: Vec<[String; 3]> = self
+            .iter()
+            .map(|(key, trace)| {
+                let overridden = trace
+                    .overridden
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                [key.to_owned(), trace.origin.to_string(), overridden]
+            })
+            .collect();
+        let key_width = rows
+            .iter()
+            .map(|row| row[0].len())
+            .chain([HEADER[0].len()])
+            .max()
+            .unwrap_or_default();
+        let origin_width = rows
+            .iter()
+            .map(|row| row[1].len())
+            .chain([HEADER[1].len()])
+            .max()
+            .unwrap_or_default();
+
+        let header = HEADER.map(String::from);
+        for [key, origin, overridden] in [&header].into_iter().chain(&rows) {
+            writeln!(
+                f,
+                "{}",
+                format!("{key:key_width$}  {origin:origin_width$}  {overridden}").trim_end()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_overrides() {
+        let base = Origin::file(Path::new("default.toml"), None);
+        let variable = Origin::EnvironmentVariable(String::from("APP_BAR__BAZ"));
+        let mut provenance = Provenance::default();
+        provenance.record(String::from("bar.baz"), base.clone());
+        provenance.record(String::from("bar.qux"), base.clone());
+        provenance.record(String::from("bar.baz"), variable.clone());
+        provenance.record(String::from("foo"), base.clone());
+        provenance.record(String::from("foo.bar"), variable.clone());
+
+        assert_eq!(
+            provenance.get("bar.baz"),
+            Some(&Trace {
+                origin: variable.clone(),
+                overridden: vec![base.clone()],
+            })
+        );
+        assert_eq!(provenance.get("foo"), None);
+        assert_eq!(
+            provenance.to_string(),
+            "KEY      SOURCE            OVERRIDES\n\
+             bar.baz  env APP_BAR__BAZ  default.toml\n\
+             bar.qux  default.toml\n\
+             foo.bar  env APP_BAR__BAZ  default.toml\n"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    interpolate::{self, Lookup},
+    Error, Provenance,
+};
+use config::{Map, Value, ValueKind};
+
+/// Expands `${key.path}` placeholders that reference other keys of the merged `tree`, e.g.
+/// `${server.port}`. Only names containing a `.` are references, others are kept as is.
+pub(crate) fn resolve(tree: &mut Map<String, Value>, provenance: &Provenance) -> Result<(), Error> {
+    let snapshot = tree.clone();
+    let mut resolver = Resolver {
+        tree: &snapshot,
+        provenance,
+        chain: Vec::new(),
+        missing: None,
+        error: None,
+    };
+    let result = interpolate::interpolate(tree, false, &mut |key, name| {
+        resolver.chain = vec![key.to_owned()];
+        resolver.lookup(name)
+    });
+    if let Some(error) = resolver.error.take() {
+        return Err(error);
+    }
+    result.map_err(|(key, message)| resolver.error(key, message))
+}
+
+struct Resolver<'a> {
+    tree: &'a Map<String, Value>,
+    provenance: &'a Provenance,
+    /// Keys being resolved, outermost first.
+    chain: Vec<String>,
+    /// The chain of the last reference to a missing key.
+    missing: Option<Vec<String>>,
+    /// Stops the resolution at the first error in a referenced value.
+    error: Option<Error>,
+}
+
+impl Resolver<'_> {
+    fn lookup(&mut self, name: &str) -> Lookup {
+        if !name.contains('.') || self.error.is_some() {
+            return Lookup::Skip;
+        }
+        self.missing = None;
+        if let Some(index) = self.chain.iter().position(|key| key == name) {
+            let mut cycle = self.chain[index..].to_vec();
+            cycle.push(name.to_owned());
+            self.error = Some(Error::ReferenceCycle(cycle));
+            return Lookup::Found(String::new());
+        }
+
+        let text = match get(self.tree, name).map(|value| &value.kind) {
+            Some(ValueKind::String(text)) => text.clone(),
+            Some(
+                kind @ (ValueKind::Boolean(_)
+                | ValueKind::I64(_)
+                | ValueKind::I128(_)
+                | ValueKind::U64(_)
+                | ValueKind::U128(_)
+                | ValueKind::Float(_)),
+            ) => kind.to_string(),
+            _ => {
+                let mut chain = self.chain.clone();
+                chain.push(name.to_owned());
+                self.missing = Some(chain);
+                return Lookup::Missing;
+            }
+        };
+
+        self.chain.push(name.to_owned());
+        let expanded = interpolate::expand(&text, false, &mut |name| self.lookup(name));
+        self.chain.pop();
+        match expanded {
+            Ok(expanded) => Lookup::Found(expanded),
+            Err(message) => {
+                self.error = Some(self.error(name.to_owned(), message));
+                Lookup::Found(String::new())
+            }
+        }
+    }
+
+    /// The error for a value of `key` that failed to expand with `message`.
+    fn error(&mut self, key: String, message: String) -> Error {
+        match self.missing.take() {
+            Some(chain) => Error::UnknownReference(chain),
+            None => {
+                let origin = self
+                    .provenance
+                    .closest(&key)
+                    .map(|trace| trace.origin.clone());
+                Error::Interpolation(key, origin, message)
+            }
+        }
+    }
+}
+
+/// The value at a dotted key path.
+fn get<'a>(tree: &'a Map<String, Value>, key: &str) -> Option<&'a Value> {
+    let (head, rest) = match key.split_once('.') {
+        Some((head, rest)) => (head, Some(rest)),
+        None => (key, None),
+    };
+    let value = tree.get(head)?;
+    match (rest, &value.kind) {
+        (None, _) => Some(value),
+        (Some(rest), ValueKind::Table(table)) => get(table, rest),
+        (Some(_), _) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merge;
+
+    fn tree(values: &[(&str, &str)]) -> Map<String, Value> {
+        let mut tree = Map::new();
+        for (key, value) in values {
+            merge::insert(&mut tree, key, Value::new(None, *value));
+        }
+        tree
+    }
+
+    #[test]
+    fn resolve_references() {
+        let mut values = tree(&[
+            ("server.host", "example.com"),
+            ("server.url", "https://${server.host}:${server.port:-443}"),
+            ("public_url", "${server.url}/${APP_PATH}"),
+        ]);
+        resolve(&mut values, &Provenance::default()).unwrap();
+        assert_eq!(
+            values,
+            tree(&[
+                ("server.host", "example.com"),
+                ("server.url", "https://example.com:443"),
+                ("public_url", "https://example.com:443/${APP_PATH}"),
+            ])
+        );
+
+        let mut values = tree(&[("a.b", "${c.d}"), ("c.d", "${e.f}"), ("e.f", "${a.b}")]);
+        let err = resolve(&mut values, &Provenance::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cyclic reference: a.b -> c.d -> e.f -> a.b"
+        );
+
+        let mut values = tree(&[("a.b", "${c.d}"), ("c.d", "${e.f}")]);
+        let err = resolve(&mut values, &Provenance::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown key `e.f` referenced by a.b -> c.d"
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{ConfigBuilder, Diff, Error, Loaded};
+use arc_swap::ArcSwap;
+use config::{Map, Value};
+use serde::de::DeserializeOwned;
+use std::sync::{mpsc, Arc, Mutex};
+
+/// Returns whether it wants to hear about further reloads.
+type Callback<Cfg> = Arc<dyn Fn(&Reload<Cfg>) -> bool + Send + Sync>;
+
+/// A config that can be reloaded while it is being read, see
+/// [`ConfigBuilder::reloadable`](crate::ConfigBuilder::reloadable).
+///
+/// Cloning is cheap, all clones share the same config.
+pub struct Reloadable<Cfg> {
+    inner: Arc<Inner<Cfg>>,
+}
+
+struct Inner<Cfg> {
+    builder: ConfigBuilder,
+    current: ArcSwap<Cfg>,
+    /// The merged values of the current config, locked for the duration of a reload so that
+    /// reloads are applied in the order they were built.
+    values: Mutex<Map<String, Value>>,
+    subscribers: Mutex<Vec<Callback<Cfg>>>,
+    /// Keeps reload triggers such as file watchers alive as long as the config is in use.
+    #[cfg(any(feature = "watch", all(unix, feature = "signal")))]
+    guards: Mutex<Vec<Box<dyn Send>>>,
+    /// The runtime that loads async sources on sync reloads.
+    #[cfg(feature = "tokio")]
+    runtime: Option<tokio::runtime::Handle>,
+}
+
+impl<Cfg> Inner<Cfg> {
+    fn new(builder: ConfigBuilder, loaded: Loaded<Cfg>) -> Self {
+        Self {
+            builder,
+            current: ArcSwap::from_pointee(loaded.config),
+            values: Mutex::new(loaded.values),
+            subscribers: Mutex::default(),
+            #[cfg(any(feature = "watch", all(unix, feature = "signal")))]
+            guards: Mutex::default(),
+            #[cfg(feature = "tokio")]
+            runtime: None,
+        }
+    }
+}
+
+/// The outcome of a reload, delivered to subscribers.
+pub enum Reload<Cfg> {
+    /// The new config is live. `diff` lists the values that changed.
+    Applied { config: Arc<Cfg>, diff: Arc<Diff> },
+    /// The new config could not be loaded, the previous one is kept.
+    Failed(Arc<Error>),
+}
+
+impl<Cfg> Clone for Reload<Cfg> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Applied { config, diff } => Self::Applied {
+                config: config.clone(),
+                diff: diff.clone(),
+            },
+            Self::Failed(error) => Self::Failed(error.clone()),
+        }
+    }
+}
+
+impl<Cfg> Clone for Reloadable<Cfg> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<Cfg> Reloadable<Cfg>
+where
+    Cfg: DeserializeOwned + Send + Sync + 'static,
+{
+    pub(crate) fn new(builder: ConfigBuilder, loaded: Loaded<Cfg>) -> Self {
+        Self {
+            inner: Arc::new(Inner::new(builder, loaded)),
+        }
+    }
+
+    /// Like [`new`](Self::new), with async sources loaded on `runtime` by sync reloads.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn with_runtime(
+        builder: ConfigBuilder,
+        loaded: Loaded<Cfg>,
+        runtime: tokio::runtime::Handle,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                runtime: Some(runtime),
+                ..Inner::new(builder, loaded)
+            }),
+        }
+    }
+
+    /// The current config.
+    pub fn load(&self) -> Arc<Cfg> {
+        self.inner.current.load_full()
+    }
+
+    /// Runs the [`ConfigBuilder`] pipeline again and swaps in the result. On failure the
+    /// current config is kept. Either way subscribers are notified.
+    ///
+    /// Async sources are loaded by blocking on the runtime the handle was built on, which
+    /// panics within async code; use `reload_async` there.
+    pub fn reload(&self) -> Reload<Cfg> {
+        self.reload_with(|builder| {
+            #[cfg(feature = "tokio")]
+            if let Some(runtime) = &self.inner.runtime {
+                return runtime.block_on(builder.build_with_metadata_async());
+            }
+            builder.build_with_metadata()
+        })
+    }
+
+    /// Loads the config with `build` and swaps it in, one reload at a time, then notifies
+    /// subscribers.
+    pub(crate) fn reload_with(
+        &self,
+        build: impl FnOnce(&ConfigBuilder) -> Result<Loaded<Cfg>, Error>,
+    ) -> Reload<Cfg> {
+        let mut values = self.inner.values.lock().unwrap_or_else(|e| e.into_inner());
+        let reload = match build(&self.inner.builder) {
+            Ok(loaded) => {
+                let diff = Arc::new(Diff::new(&values, &loaded.values));
+                let config = Arc::new(loaded.config);
+                self.inner.current.store(config.clone());
+                *values = loaded.values;
+                Reload::Applied { config, diff }
+            }
+            Err(e) => Reload::Failed(Arc::new(e)),
+        };
+        drop(values);
+        self.notify(&reload);
+        reload
+    }
+
+    /// Calls `callback` after every reload attempt.
+    pub fn on_reload(&self, callback: impl Fn(&Reload<Cfg>) + Send + Sync + 'static) {
+        self.on_reload_while(move |reload| {
+            callback(reload);
+            true
+        });
+    }
+
+    /// Calls `callback` after every reload attempt until it returns `false`.
+    pub(crate) fn on_reload_while(
+        &self,
+        callback: impl Fn(&Reload<Cfg>) -> bool + Send + Sync + 'static,
+    ) {
+        self.lock_subscribers().push(Arc::new(callback));
+    }
+
+    /// Sends every reload attempt to the returned channel.
+    pub fn subscribe(&self) -> mpsc::Receiver<Reload<Cfg>> {
+        let (sender, receiver) = mpsc::channel();
+        // A dropped receiver only means nobody is interested anymore.
+        self.on_reload_while(move |reload| sender.send(reload.clone()).is_ok());
+        receiver
+    }
+
+    pub(crate) fn notify(&self, reload: &Reload<Cfg>) {
+        // Called without the lock, so that subscribers may subscribe or reload themselves.
+        let subscribers = self.lock_subscribers().clone();
+        let done: Vec<_> = subscribers
+            .into_iter()
+            .filter(|subscriber| !subscriber(reload))
+            .collect();
+        if !done.is_empty() {
+            self.lock_subscribers()
+                .retain(|subscriber| !done.iter().any(|done| Arc::ptr_eq(subscriber, done)));
+        }
+    }
+
+    fn lock_subscribers(&self) -> std::sync::MutexGuard<'_, Vec<Callback<Cfg>>> {
+        self.inner
+            .subscribers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Plumbing for reload triggers.
+#[cfg(any(feature = "watch", all(unix, feature = "signal")))]
+impl<Cfg> Reloadable<Cfg> {
+    pub(crate) fn downgrade(&self) -> Weak<Cfg> {
+        Weak(Arc::downgrade(&self.inner))
+    }
+
+    pub(crate) fn keep_alive(&self, guard: impl Send + 'static) {
+        self.inner
+            .guards
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Box::new(guard));
+    }
+}
+
+/// A handle that does not keep the config alive, held by reload triggers.
+#[cfg(any(feature = "watch", all(unix, feature = "signal")))]
+pub(crate) struct Weak<Cfg>(std::sync::Weak<Inner<Cfg>>);
+
+#[cfg(any(feature = "watch", all(unix, feature = "signal")))]
+impl<Cfg> Weak<Cfg> {
+    pub(crate) fn upgrade(&self) -> Option<Reloadable<Cfg>> {
+        self.0.upgrade().map(|inner| Reloadable { inner })
+    }
+}
+
+impl ConfigBuilder {
+    /// Builds the config into a [`Reloadable`] handle that can be reloaded on demand.
+    pub fn reloadable<Cfg>(&self) -> Result<Reloadable<Cfg>, Error>
+    where
+        Cfg: DeserializeOwned + Send + Sync + 'static,
+    {
+        Ok(Reloadable::new(self.clone(), self.build_with_metadata()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ConfigBuilder;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct FooConfig {}
+
+    #[test]
+    fn dropped_subscriptions() {
+        std::env::set_var("SUBSCRIBED_ENV", "production");
+        let config = ConfigBuilder::default()
+            .environment_variable_name("SUBSCRIBED_ENV")
+            .environment_variables_source_prefix("SUBSCRIBED")
+            .config_directory(format!(
+                "{}/tests/fixtures/layered",
+                env!("CARGO_MANIFEST_DIR")
+            ))
+            .reloadable::<FooConfig>()
+            .unwrap();
+
+        let kept = config.subscribe();
+        drop(config.subscribe());
+        #[cfg(feature = "tokio")]
+        drop(config.changes());
+        assert_eq!(
+            config.lock_subscribers().len(),
+            if cfg!(feature = "tokio") { 3 } else { 2 }
+        );
+        config.reload();
+        assert_eq!(config.lock_subscribers().len(), 1);
+        assert!(kept.try_recv().is_ok());
+
+        let nested = config.clone();
+        config.on_reload(move |_| drop(nested.subscribe()));
+        config.reload();
+        assert_eq!(config.lock_subscribers().len(), 3);
+    }
+}
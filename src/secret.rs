@@ -0,0 +1,230 @@
+use config::{Map, Value, ValueKind};
+use serde::{de, Deserialize, Deserializer};
+use std::{
+    collections::{hash_map::RandomState, BTreeSet},
+    fmt,
+    hash::BuildHasher,
+    marker::PhantomData,
+    sync::OnceLock,
+};
+use zeroize::Zeroize;
+
+/// The newtype name [`Secret`] deserializes as, so that errors about its value are redacted.
+pub(crate) const NAME: &str = "cfgio::Secret";
+
+/// A config value that is redacted when printed and zeroed when dropped, e.g. a password.
+///
+/// Deserializes like `T` from any source, the value is only reachable through
+/// [`expose`](Self::expose). Errors about an invalid value quote neither the value nor the line
+/// of the file it came from.
+#[derive(Clone, Default)]
+pub struct Secret<T: Zeroize>(T);
+
+impl<T: Zeroize> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl<T: Zeroize> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl<'de, T: Zeroize + Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(NAME, SecretVisitor(PhantomData))
+    }
+}
+
+struct SecretVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Zeroize + Deserialize<'de>> de::Visitor<'de> for SecretVisitor<T> {
+    type Value = Secret<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a secret")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        T::deserialize(deserializer).map(Secret)
+    }
+}
+
+/// Replaces the values at and under `keys` with fingerprints, so that merged values kept around
+/// for diffing still tell changed secrets apart without holding them in plaintext.
+pub(crate) fn fingerprint(tree: &mut Map<String, Value>, keys: &BTreeSet<String>) {
+    for (key, value) in tree.iter_mut() {
+        fingerprint_value(key, value, keys, false);
+    }
+}
+
+fn fingerprint_value(key: &str, value: &mut Value, keys: &BTreeSet<String>, secret: bool) {
+    // Keyed per process, so fingerprints can't be matched against guesses elsewhere.
+    static STATE: OnceLock<RandomState> = OnceLock::new();
+    let secret = secret || keys.contains(key);
+    match &mut value.kind {
+        ValueKind::Table(table) => {
+            for (child, value) in table.iter_mut() {
+                fingerprint_value(&format!("{key}.{child}"), value, keys, secret);
+            }
+        }
+        ValueKind::Array(array) => {
+            for (index, value) in array.iter_mut().enumerate() {
+                fingerprint_value(&format!("{key}[{index}]"), value, keys, secret);
+            }
+        }
+        ValueKind::Nil => {}
+        kind if secret => {
+            let hash = STATE
+                .get_or_init(RandomState::new)
+                .hash_one(kind.to_string());
+            *kind = ValueKind::String(format!("[REDACTED {hash:016x}]"));
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{de, merge, ConfigBuilder};
+
+    #[test]
+    fn redacted() {
+        #[derive(Debug, Deserialize)]
+        struct Database {
+            password: Secret<String>,
+            port: Secret<u16>,
+        }
+        let mut tree = Map::new();
+        merge::insert(&mut tree, "password", Value::new(None, "hunter2"));
+        merge::insert(&mut tree, "port", Value::new(None, "5432"));
+
+        let (database, secrets): (Database, _) =
+            de::deserialize(tree, false).unwrap_or_else(|_| panic!());
+        assert_eq!(secrets, ["password", "port"].map(String::from).into());
+        assert_eq!(database.password.expose(), "hunter2");
+        assert_eq!(*database.port.expose(), 5432);
+        assert_eq!(database.password.to_string(), "[REDACTED]");
+        assert_eq!(
+            format!("{database:?}"),
+            "Database { password: [REDACTED], port: [REDACTED] }"
+        );
+    }
+
+    #[test]
+    fn redacted_errors() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct BarConfig {
+            baz: Secret<u8>,
+            list: Secret<Vec<u16>>,
+            missing: Secret<String>,
+        }
+        std::env::set_var("REDACTED_ENV", "production");
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("REDACTED_ENV")
+            .environment_variables_source_prefix("REDACTED")
+            .config_directory(format!(
+                "{}/tests/fixtures/invalid",
+                env!("CARGO_MANIFEST_DIR")
+            ));
+
+        let Err(crate::Error::Deserialization(error)) = builder.build::<FooConfig>() else {
+            panic!("expected a deserialization error");
+        };
+        assert_eq!(error.message, "invalid secret value, expected u8");
+        assert_eq!(error.snippet, None);
+        assert!(!error.to_string().contains("300"));
+
+        builder.collect_errors(true);
+        let Err(crate::Error::Multiple(errors)) = builder.build::<FooConfig>() else {
+            panic!("expected multiple errors");
+        };
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|e| (e.key.as_str(), e.message.as_str(), e.snippet.as_deref()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ("bar.baz", "invalid secret value, expected u8", None),
+                ("bar.list[1]", "invalid secret value, expected u16", None),
+                ("bar.missing", "missing field `missing`", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn fingerprinted() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct FooConfig {
+            database: DatabaseConfig,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct DatabaseConfig {
+            password: Secret<String>,
+            user: String,
+        }
+        std::env::set_var("FINGERPRINT_ENV", "production");
+        std::env::set_var("FINGERPRINT_DATABASE__PASSWORD", "hunter2");
+        std::env::set_var("FINGERPRINT_DATABASE__USER", "admin");
+        let mut builder = ConfigBuilder::default();
+        builder
+            .environment_variable_name("FINGERPRINT_ENV")
+            .environment_variables_source_prefix("FINGERPRINT")
+            .config_directory(format!(
+                "{}/tests/fixtures/layered",
+                env!("CARGO_MANIFEST_DIR")
+            ));
+
+        let first = builder.build_with_metadata::<FooConfig>().unwrap();
+        assert!(!format!("{first:?}").contains("hunter2"));
+        assert!(!format!("{:?}", first.values).contains("hunter2"));
+        assert!(format!("{:?}", first.values).contains("admin"));
+
+        let same = builder.build_with_metadata::<FooConfig>().unwrap();
+        assert!(same.diff(&first).is_empty());
+
+        std::env::set_var("FINGERPRINT_DATABASE__PASSWORD", "hunter3");
+        let changed = builder.build_with_metadata::<FooConfig>().unwrap();
+        assert!(changed.diff(&first).touches("database.password"));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{Error, Reload, Reloadable};
+use serde::de::DeserializeOwned;
+use signal_hook::{
+    consts::{SIGHUP, SIGUSR1},
+    iterator::{backend::Handle, Signals},
+};
+use std::fmt;
+
+/// Unix signals that can trigger a reload, see [`Reloadable::reload_on_signals`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReloadSignal {
+    Hangup,
+    User1,
+}
+
+impl ReloadSignal {
+    fn number(self) -> i32 {
+        match self {
+            Self::Hangup => SIGHUP,
+            Self::User1 => SIGUSR1,
+        }
+    }
+}
+
+impl fmt::Display for ReloadSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Hangup => "SIGHUP",
+            Self::User1 => "SIGUSR1",
+        })
+    }
+}
+
+/// Stops listening for signals once the config is dropped.
+struct Listener(Handle);
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+impl<Cfg> Reloadable<Cfg>
+where
+    Cfg: DeserializeOwned + Send + Sync + 'static,
+{
+    /// Reloads the config whenever the process receives one of `signals`, conventionally
+    /// [`ReloadSignal::Hangup`]. Failed reloads are logged and the current config is kept.
+    ///
+    /// Listening stops once every clone of the handle is dropped.
+    pub fn reload_on_signals(&self, signals: &[ReloadSignal]) -> Result<(), Error> {
+        let mut listener =
+            Signals::new(signals.iter().map(|signal| signal.number())).map_err(Error::Signal)?;
+        let signals = signals.to_vec();
+        let handle = self.downgrade();
+        self.keep_alive(Listener(listener.handle()));
+
+        std::thread::spawn(move || {
+            for number in listener.forever() {
+                let Some(reloadable) = handle.upgrade() else {
+                    break;
+                };
+                if let Reload::Failed(e) = reloadable.reload() {
+                    let signal = signals.iter().find(|signal| signal.number() == number);
+                    log::error!("Failed to reload config on {}: {e}", signal.unwrap());
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ConfigBuilder;
+    use serde::Deserialize;
+    use std::time::Duration;
+
+    #[test]
+    fn reload_on_signal() {
+        #[derive(Deserialize)]
+        struct FooConfig {
+            bar: BarConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct BarConfig {
+            baz: u16,
+        }
+        std::env::set_var("SIGNAL_ENV", "production");
+        std::env::set_var("SIGNAL_BAR__BAZ", "1");
+        let config = ConfigBuilder::default()
+            .environment_variable_name("SIGNAL_ENV")
+            .environment_variables_source_prefix("SIGNAL")
+            .config_directory(format!(
+                "{}/tests/fixtures/layered",
+                env!("CARGO_MANIFEST_DIR")
+            ))
+            .reloadable::<FooConfig>()
+            .unwrap();
+        config.reload_on_signals(&[ReloadSignal::User1]).unwrap();
+        let reloads = config.subscribe();
+        assert_eq!(config.load().bar.baz, 1);
+
+        std::env::set_var("SIGNAL_BAR__BAZ", "2");
+        signal_hook::low_level::raise(SIGUSR1).unwrap();
+        assert!(matches!(
+            reloads.recv_timeout(Duration::from_secs(5)).unwrap(),
+            Reload::Applied { .. }
+        ));
+        assert_eq!(config.load().bar.baz, 2);
+
+        std::env::set_var("SIGNAL_BAR__BAZ", "x");
+        signal_hook::low_level::raise(SIGUSR1).unwrap();
+        assert!(matches!(
+            reloads.recv_timeout(Duration::from_secs(5)).unwrap(),
+            Reload::Failed(_)
+        ));
+        assert_eq!(config.load().bar.baz, 2);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{
+    merge::{self, Collected},
+    Error, Layer, Origin, Priority,
+};
+use config::{Map, Value};
+use std::{fmt, sync::Arc};
+
+/// A custom provider of config values, merged at a chosen [`Priority`], see
+/// [`ConfigBuilder::add_source`](crate::ConfigBuilder::add_source).
+///
+/// ```
+/// use cfgio::{Source, Value};
+///
+/// struct Defaults;
+///
+/// impl Source for Defaults {
+///     fn name(&self) -> &str {
+///         "defaults"
+///     }
+///
+///     fn collect(
+///         &self,
+///     ) -> Result<Vec<(String, Value)>, Box<dyn std::error::Error + Send + Sync>> {
+///         Ok(vec![(String::from("server.port"), 8080.into())])
+///     }
+/// }
+/// ```
+pub trait Source: Send + Sync {
+    /// Identifies the source in provenance, [`Layer::Source`] and [`Error::Source`].
+    fn name(&self) -> &str;
+
+    /// Values by dotted key path such as `server.port`.
+    fn collect(&self) -> Result<Vec<(String, Value)>, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// A custom provider of config values that is loaded asynchronously, e.g. over HTTP, see
+/// [`ConfigBuilder::add_async_source`](crate::ConfigBuilder::add_async_source).
+///
+/// Async sources are only loaded by [`build_async`](crate::ConfigBuilder::build_async), all of
+/// them concurrently.
+#[cfg(feature = "tokio")]
+pub trait AsyncSource: Send + Sync {
+    /// Identifies the source in provenance, [`Layer::Source`] and errors.
+    fn name(&self) -> &str;
+
+    /// Values by dotted key path such as `server.port`.
+    fn collect(&self) -> BoxFuture<'_, Result<Vec<(String, Value)>, BoxError>>;
+
+    /// How long [`collect`](Self::collect) may take, `async_source_timeout` by default.
+    fn timeout(&self) -> Option<std::time::Duration> {
+        None
+    }
+
+    /// Whether the build may go on without this source when it fails or times out, see
+    /// `skip_failed_optional_sources`.
+    fn optional(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "tokio")]
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The future returned by [`AsyncSource::collect`].
+#[cfg(feature = "tokio")]
+pub type BoxFuture<'a, T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send + 'a>>;
+
+/// The custom sources of a [`Config`](crate::Config), with the priority they are merged at.
+#[derive(Clone, Default)]
+pub(crate) struct Sources(pub(crate) Vec<(Priority, Arc<dyn Source>)>);
+
+impl fmt::Debug for Sources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.0
+                    .iter()
+                    .map(|(priority, source)| (priority, source.name())),
+            )
+            .finish()
+    }
+}
+
+/// The async sources of a [`Config`](crate::Config), with the priority they are merged at.
+#[cfg(feature = "tokio")]
+#[derive(Clone, Default)]
+pub(crate) struct AsyncSources(pub(crate) Vec<(Priority, Arc<dyn AsyncSource>)>);
+
+#[cfg(feature = "tokio")]
+impl fmt::Debug for AsyncSources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.0
+                    .iter()
+                    .map(|(priority, source)| (priority, source.name())),
+            )
+            .finish()
+    }
+}
+
+/// Collects `source` into a layer whose values all originate from it.
+pub(crate) fn collect(source: &dyn Source) -> Result<Collected, Error> {
+    let name = source.name();
+    let entries = source
+        .collect()
+        .map_err(|e| Error::Source(name.to_owned(), e))?;
+    Ok(layer(name, entries))
+}
+
+/// A layer of the values collected from the source called `name`.
+pub(crate) fn layer(name: &str, entries: Vec<(String, Value)>) -> Collected {
+    let mut values = Map::new();
+    let mut origins = Vec::new();
+    for (key, value) in entries {
+        merge::insert(&mut values, &key, value);
+        origins.push((key, Origin::Source(name.to_owned())));
+    }
+
+    Collected {
+        layer: Layer::Source(name.to_owned()),
+        values,
+        origins,
+    }
+}
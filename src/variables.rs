@@ -0,0 +1,75 @@
+use crate::{merge, merge::Collected, Error, Layer, Origin};
+use config::{Map, Value};
+use std::path::PathBuf;
+
+/// Collects the environment variables starting with `prefix` followed by `prefix_separator`,
+/// following the key mapping of [`config::Environment`].
+///
+/// `defaults` are variables read from elsewhere, e.g. `.env` files, that the process
+/// environment overrides. Variables ending with `file_suffix` name a file to read the value
+/// from, e.g. `APP_DATABASE__PASSWORD_FILE` sets `database.password`, unless nothing is left
+/// between the prefix and the suffix.
+pub(crate) fn collect(
+    prefix: &str,
+    prefix_separator: &str,
+    separator: &str,
+    file_suffix: Option<&str>,
+    defaults: Vec<(String, String, Origin)>,
+) -> Result<Option<Collected>, Error> {
+    let prefix_pattern = format!("{prefix}{prefix_separator}").to_lowercase();
+    let mut environment: Vec<_> = std::env::vars()
+        .map(|(name, value)| {
+            let origin = Origin::EnvironmentVariable(name.clone());
+            (name, value, origin)
+        })
+        .collect();
+    environment.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+    let variables: Vec<_> = defaults
+        .into_iter()
+        .chain(environment)
+        .filter(|(name, ..)| name.to_lowercase().starts_with(&prefix_pattern))
+        .collect();
+    if variables.is_empty() {
+        return Ok(None);
+    }
+
+    let mut names = Vec::new();
+    let mut values = Map::new();
+    let mut origins = Vec::new();
+    for (name, mut value, mut origin) in variables {
+        let mut key_name = name.as_str();
+        // `APP_FILE` is a plain variable, not a file reference with an empty key.
+        let stripped = file_suffix
+            .and_then(|suffix| name.strip_suffix(suffix))
+            .filter(|stripped| {
+                stripped
+                    .to_lowercase()
+                    .strip_prefix(&prefix_pattern)
+                    .is_some_and(|rest| !rest.is_empty())
+            });
+        if let Some(stripped) = stripped {
+            let path = PathBuf::from(&value);
+            value = std::fs::read_to_string(&path)
+                .map_err(|e| Error::EnvironmentVariableFileAccess(name.clone(), path.clone(), e))?
+                .trim_end_matches(['\n', '\r'])
+                .to_owned();
+            origin = Origin::file(&path, None);
+            key_name = stripped;
+        }
+        let mut key = key_name.to_lowercase()[prefix_pattern.len()..].to_owned();
+        if !separator.is_empty() {
+            key = key.replace(separator, ".");
+        }
+        merge::insert(&mut values, &key, Value::new(Some(&name), value));
+        origins.push((key, origin));
+        names.push(name);
+    }
+    names.sort();
+    names.dedup();
+
+    Ok(Some(Collected {
+        layer: Layer::EnvironmentVariables(names),
+        values,
+        origins,
+    }))
+}
@@ -0,0 +1,117 @@
+use crate::{ConfigBuilder, Error, Reload, Reloadable};
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebounceEventResult};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+impl ConfigBuilder {
+    /// Builds the config into a [`Reloadable`] handle that is reloaded whenever a file under
+    /// `config_directory` changes. Changes are debounced by `watch_debounce`.
+    ///
+    /// Watching stops once every clone of the handle is dropped.
+    pub fn watch<Cfg>(&self) -> Result<Reloadable<Cfg>, Error>
+    where
+        Cfg: DeserializeOwned + Send + Sync + 'static,
+    {
+        let reloadable = self.reloadable()?;
+        self.watch_config_directory(&reloadable)?;
+        Ok(reloadable)
+    }
+
+    /// Like [`watch`](Self::watch), but builds with
+    /// [`reloadable_async`](Self::reloadable_async).
+    #[cfg(feature = "tokio")]
+    pub async fn watch_async<Cfg>(&self) -> Result<Reloadable<Cfg>, Error>
+    where
+        Cfg: DeserializeOwned + Send + Sync + 'static,
+    {
+        let reloadable = self.reloadable_async().await?;
+        self.watch_config_directory(&reloadable)?;
+        Ok(reloadable)
+    }
+
+    fn watch_config_directory<Cfg>(&self, reloadable: &Reloadable<Cfg>) -> Result<(), Error>
+    where
+        Cfg: DeserializeOwned + Send + Sync + 'static,
+    {
+        let config = self.prepare().map_err(Error::Preparation)?;
+        let config_directory = std::env::current_dir()
+            .map_err(Error::WorkingDirectoryAccess)?
+            .join(&config.config_directory);
+
+        let handle = reloadable.downgrade();
+        let mut debouncer =
+            new_debouncer(config.watch_debounce, move |result: DebounceEventResult| {
+                let Some(reloadable) = handle.upgrade() else {
+                    return;
+                };
+                match result {
+                    Ok(_) => {
+                        reloadable.reload();
+                    }
+                    Err(e) => reloadable.notify(&Reload::Failed(Arc::new(Error::Watch(e)))),
+                }
+            })
+            .map_err(Error::Watch)?;
+        debouncer
+            .watcher()
+            .watch(&config_directory, RecursiveMode::Recursive)
+            .map_err(Error::Watch)?;
+        reloadable.keep_alive(debouncer);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ConfigBuilder, Reload};
+    use serde::Deserialize;
+    use std::time::Duration;
+
+    #[derive(Deserialize)]
+    struct FooConfig {
+        bar: BarConfig,
+    }
+
+    #[derive(Deserialize)]
+    struct BarConfig {
+        baz: u16,
+    }
+
+    #[test]
+    fn reload_on_change() {
+        let directory = std::env::temp_dir().join(format!("cfgio-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let config_file = directory.join("local.toml");
+        std::fs::write(&config_file, "[bar]\nbaz = 1\n").unwrap();
+
+        let config = ConfigBuilder::default()
+            .environment_variable_name("WATCH_ENV")
+            .environment_variables_source_prefix("WATCH")
+            .config_directory(directory.to_string_lossy())
+            .watch_debounce(Duration::from_millis(50))
+            .watch::<FooConfig>()
+            .unwrap();
+        let reloads = config.subscribe();
+        assert_eq!(config.load().bar.baz, 1);
+
+        std::fs::write(&config_file, "[bar]\nbaz = 2\n").unwrap();
+        match reloads.recv_timeout(Duration::from_secs(5)).unwrap() {
+            Reload::Applied { config, diff } => {
+                assert_eq!(config.bar.baz, 2);
+                assert_eq!(diff.to_string(), "~ bar.baz\n");
+            }
+            Reload::Failed(e) => panic!("{e}"),
+        }
+        assert_eq!(config.load().bar.baz, 2);
+
+        std::fs::write(&config_file, "[bar]\nbaz = \"x\"\n").unwrap();
+        assert!(matches!(
+            reloads.recv_timeout(Duration::from_secs(5)).unwrap(),
+            Reload::Failed(_)
+        ));
+        assert_eq!(config.load().bar.baz, 2);
+
+        drop(config);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}